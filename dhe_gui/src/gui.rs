@@ -41,7 +41,7 @@ impl Application for App {
         Command::none()
     }
 
-    fn view(&self) -> Element<Self::Message> {
+    fn view(&self) -> Element<'_, Self::Message> {
         column![
             button("Increment").on_press(Message::Increment),
             text(self.value).size(50),
@@ -192,9 +192,9 @@ async fn generate_menu(amount: u8, db_conn: &DatabaseConnection) -> Result<Menu,
     let dishes: Vec<_> = dishes?
         .into_iter()
         .filter(|d| match d.amount.cmp(&amount) {
-            Less => amount % d.amount == 0,
+            Less => amount.is_multiple_of(d.amount),
             Equal => true,
-            Greater => d.amount % amount == 0,
+            Greater => d.amount.is_multiple_of(amount),
         })
         .map(|mut d| {
             match d.amount.cmp(&amount) {
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+use crate::{
+    keyboard::KeyboardError,
+    language::{Language, LanguageError},
+    translate::{TranslateError, Translation},
+};
+
+#[derive(Error, Debug)]
+pub enum ActionError {
+    #[error("unknown action \"{0}\"")]
+    UnknownAction(String),
+    #[error("wrong options of action \"{0}\": {1}")]
+    WrongOptions(String, String),
+    #[error("language error: {0}")]
+    Language(LanguageError),
+    #[error("translate error: {0}")]
+    Translate(TranslateError),
+    #[error("keyboard error: {0}")]
+    Keyboard(KeyboardError),
+    #[error("action failed: {0}")]
+    Failed(String),
+}
+
+/// Everything an action works with: the selection, the clipboard, the emulated keyboard
+/// and the state of the listener at the moment the action fired.
+#[async_trait]
+pub trait ActionContext: Send + Sync {
+    /// Language of the active keyboard layout, if the layout is tracked.
+    fn layout(&self) -> Option<Language>;
+
+    /// Language of the text, the active layout is used as a prior.
+    fn detect_language(&self, text: &str) -> Result<Language, ActionError>;
+
+    /// Text retyped in the layout where it is clearly more like a real text,
+    /// `None` if the text is typed in the right layout or it is not clear.
+    fn fix_layout(&self, text: &str) -> Option<String>;
+
+    async fn translate(
+        &self,
+        text: &str,
+        from: Language,
+        to: Language,
+    ) -> Result<Translation, ActionError>;
+
+    /// Text of the primary selection.
+    async fn selected_text(&self) -> Result<String, ActionError>;
+
+    async fn clipboard_text(&self) -> Result<String, ActionError>;
+
+    async fn set_clipboard_text(&self, text: String) -> Result<(), ActionError>;
+
+    /// Insert the text in place of the selection.
+    async fn paste(&self, text: String) -> Result<(), ActionError>;
+
+    async fn notify(&self, body: &str) -> Result<(), ActionError>;
+
+    /// Retype the last typed word in the other layout once the keys are released.
+    fn retype_last_word(&self) -> Result<(), ActionError>;
+}
+
+/// Something done when the keys of a binding are pressed.
+#[async_trait]
+pub trait Action: Send + Sync {
+    async fn run(&self, ctx: &dyn ActionContext) -> Result<(), ActionError>;
+
+    /// Check the options of the action once it is built.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Whether the action runs before the next keys are read instead of a separate task,
+    /// for quick actions that depend on the keys typed before them.
+    fn is_inline(&self) -> bool {
+        false
+    }
+}
+
+type ActionBuilder = Box<dyn Fn(toml::Table) -> Result<Box<dyn Action>, String> + Send + Sync>;
+
+/// Action types by their names, an action is built from the options of its binding.
+#[derive(Default)]
+pub struct ActionRegistry {
+    builders: HashMap<String, ActionBuilder>,
+}
+
+impl ActionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an action type, the action itself is deserialized from the binding options.
+    pub fn register<T: Action + DeserializeOwned + 'static>(&mut self, name: &str) {
+        let builder = |options: toml::Table| {
+            let action: T = toml::Value::Table(options)
+                .try_into()
+                .map_err(|err: toml::de::Error| err.message().to_string())?;
+            action.validate()?;
+            Ok(Box::new(action) as Box<dyn Action>)
+        };
+        self.builders.insert(name.to_string(), Box::new(builder));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.builders.contains_key(name)
+    }
+
+    pub fn build(&self, name: &str, options: toml::Table) -> Result<Box<dyn Action>, ActionError> {
+        let builder = self
+            .builders
+            .get(name)
+            .ok_or_else(|| ActionError::UnknownAction(name.to_string()))?;
+        builder(options).map_err(|err| ActionError::WrongOptions(name.to_string(), err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Repeat {
+        times: usize,
+    }
+
+    #[async_trait]
+    impl Action for Repeat {
+        async fn run(&self, ctx: &dyn ActionContext) -> Result<(), ActionError> {
+            let text = ctx.selected_text().await?;
+            ctx.paste(text.repeat(self.times)).await
+        }
+
+        fn validate(&self) -> Result<(), String> {
+            if self.times == 0 {
+                return Err("nothing to repeat".to_string());
+            }
+            Ok(())
+        }
+    }
+
+    fn options(toml: &str) -> toml::Table {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn build_registered_actions() {
+        let mut registry = ActionRegistry::new();
+        registry.register::<Repeat>("repeat");
+
+        assert!(registry.contains("repeat"));
+        assert!(registry.build("repeat", options("times = 2")).is_ok());
+        assert!(matches!(
+            registry.build("echo", options("")),
+            Err(ActionError::UnknownAction(name)) if name == "echo"
+        ));
+        assert!(matches!(
+            registry.build("repeat", options("times = 0")),
+            Err(ActionError::WrongOptions(_, err)) if err == "nothing to repeat"
+        ));
+        assert!(matches!(
+            registry.build("repeat", options("times = 2\ncount = 3")),
+            Err(ActionError::WrongOptions(_, err)) if err.contains("count")
+        ));
+    }
+}
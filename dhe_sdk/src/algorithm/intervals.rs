@@ -23,7 +23,7 @@ pub fn get_distances(arr: Vec<i32>) -> Vec<i64> {
         counter
             .entry(value)
             .and_modify(|(len, sum): &mut (usize, usize)| {
-                result[index] += (*len * index - *sum) as i64;
+                result[index] += (*sum - *len * index) as i64;
                 *len += 1;
                 *sum += index;
             })
@@ -0,0 +1,202 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use evdev::Device;
+use regex::Regex;
+
+use super::{EMULATOR_DEVICE_NAME, FORWARD_DEVICE_NAME};
+
+/// Directory of the persistent device links by the device id.
+const BY_ID_DIR: &str = "/dev/input/by-id";
+
+/// Kind of the input devices the listener reads.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, strum::Display)]
+pub enum DeviceKind {
+    Keyboard,
+    /// Mouse or another device with mouse buttons, read only for bindings with mouse keys.
+    Pointer,
+}
+
+/// Input device description the filters are matched against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub kind: DeviceKind,
+    pub vendor: u16,
+    pub product: u16,
+    /// Device node like `/dev/input/event3`.
+    pub path: PathBuf,
+    /// Links of the `/dev/input/by-id` directory that point to the node.
+    pub links: Vec<PathBuf>,
+}
+
+impl DeviceInfo {
+    pub(crate) fn new(path: &Path, device: &Device, kind: DeviceKind) -> Self {
+        let id = device.input_id();
+        Self {
+            name: device.name().unwrap_or_default().to_string(),
+            kind,
+            vendor: id.vendor(),
+            product: id.product(),
+            path: path.to_path_buf(),
+            links: by_id_links(path),
+        }
+    }
+}
+
+/// Device rule, all the set fields have to match.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceRule {
+    pub name: Option<Regex>,
+    pub vendor: Option<u16>,
+    pub product: Option<u16>,
+    /// Device node or a `/dev/input/by-id` link.
+    pub path: Option<PathBuf>,
+}
+
+impl DeviceRule {
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        self.name
+            .as_ref()
+            .is_none_or(|name| name.is_match(&device.name))
+            && self.vendor.is_none_or(|vendor| vendor == device.vendor)
+            && self.product.is_none_or(|product| product == device.product)
+            && self
+                .path
+                .as_ref()
+                .is_none_or(|path| *path == device.path || device.links.contains(path))
+    }
+}
+
+/// Keyboards the listener reads: all of them by default, only the included ones
+/// if there are include rules, but never the excluded ones.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceFilter {
+    pub include: Vec<DeviceRule>,
+    pub exclude: Vec<DeviceRule>,
+}
+
+impl DeviceFilter {
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|rule| rule.matches(device)))
+            && !self.exclude.iter().any(|rule| rule.matches(device))
+    }
+}
+
+/// Input devices that look like keyboards or pointers, the filters choose among them.
+pub fn input_devices() -> Vec<DeviceInfo> {
+    let mut devices: Vec<DeviceInfo> = evdev::enumerate()
+        .filter_map(|(path, device)| {
+            device_kind(&device).map(|kind| DeviceInfo::new(&path, &device, kind))
+        })
+        .collect();
+    devices.sort_by(|a, b| a.path.cmp(&b.path));
+    devices
+}
+
+/// Kind of the device if the listener can read it, the own virtual devices are skipped.
+pub(crate) fn device_kind(device: &Device) -> Option<DeviceKind> {
+    if matches!(
+        device.name(),
+        Some(EMULATOR_DEVICE_NAME | FORWARD_DEVICE_NAME)
+    ) {
+        return None;
+    }
+    let keys = device.supported_keys()?;
+    if keys.contains(evdev::Key::KEY_ENTER) {
+        Some(DeviceKind::Keyboard)
+    } else if keys.contains(evdev::Key::BTN_LEFT) {
+        Some(DeviceKind::Pointer)
+    } else {
+        None
+    }
+}
+
+fn by_id_links(path: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(BY_ID_DIR) else {
+        return vec![];
+    };
+    let mut links: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|link| fs::canonicalize(link).is_ok_and(|target| target == path))
+        .collect();
+    links.sort();
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(name: &str, vendor: u16, product: u16) -> DeviceInfo {
+        DeviceInfo {
+            name: name.to_string(),
+            kind: DeviceKind::Keyboard,
+            vendor,
+            product,
+            path: PathBuf::from("/dev/input/event3"),
+            links: vec![PathBuf::from(
+                "/dev/input/by-id/usb-Yubico_YubiKey-event-kbd",
+            )],
+        }
+    }
+
+    #[test]
+    fn include_and_exclude() {
+        let keyboard = device("AT Translated Set 2 keyboard", 0x0001, 0x0001);
+        let yubikey = device("Yubico YubiKey OTP+FIDO+CCID", 0x1050, 0x0407);
+
+        let filter = DeviceFilter::default();
+        assert!(filter.matches(&keyboard));
+        assert!(filter.matches(&yubikey));
+
+        let filter = DeviceFilter {
+            include: vec![],
+            exclude: vec![DeviceRule {
+                vendor: Some(0x1050),
+                ..Default::default()
+            }],
+        };
+        assert!(filter.matches(&keyboard));
+        assert!(!filter.matches(&yubikey));
+
+        let filter = DeviceFilter {
+            include: vec![DeviceRule {
+                name: Some(Regex::new("(?i)keyboard").unwrap()),
+                ..Default::default()
+            }],
+            exclude: vec![],
+        };
+        assert!(filter.matches(&keyboard));
+        assert!(!filter.matches(&yubikey));
+    }
+
+    #[test]
+    fn rule_fields() {
+        let yubikey = device("Yubico YubiKey OTP+FIDO+CCID", 0x1050, 0x0407);
+
+        let rule = DeviceRule {
+            vendor: Some(0x1050),
+            product: Some(0x0408),
+            ..Default::default()
+        };
+        assert!(!rule.matches(&yubikey));
+
+        let rule = DeviceRule {
+            path: Some(PathBuf::from(
+                "/dev/input/by-id/usb-Yubico_YubiKey-event-kbd",
+            )),
+            ..Default::default()
+        };
+        assert!(rule.matches(&yubikey));
+
+        let rule = DeviceRule {
+            path: Some(PathBuf::from("/dev/input/event3")),
+            ..Default::default()
+        };
+        assert!(rule.matches(&yubikey));
+    }
+}
@@ -0,0 +1,151 @@
+use super::{Key, KeyboardState, Led, LedEvent};
+use crate::language::Language;
+
+/// The way the active XKB layout group is switched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutSwitch {
+    /// Switching to the next layout when all the keys become pressed.
+    Keys(Vec<Key>),
+    /// The group is shown by a LED (the `grp_led` XKB option):
+    /// the first layout is active when the LED is off and the second one when it is on.
+    Led(Led),
+}
+
+/// Tracking of the active keyboard layout, used as a language detection hint.
+#[derive(Debug, Clone)]
+pub struct LayoutTracker {
+    layouts: Vec<Language>,
+    active: usize,
+    switch: LayoutSwitch,
+    switch_state: KeyboardState,
+    switch_pressed: bool,
+}
+
+impl LayoutTracker {
+    /// Tracker of the given layouts in the XKB group order, the first one is active initially.
+    pub fn new(layouts: Vec<Language>, switch: LayoutSwitch) -> Self {
+        let mut switch_state = KeyboardState::default();
+        if let LayoutSwitch::Keys(keys) = &switch {
+            switch_state.apply_keys(keys);
+        }
+        Self {
+            layouts,
+            active: 0,
+            switch,
+            switch_state,
+            switch_pressed: false,
+        }
+    }
+
+    /// Languages of the tracked layouts in the XKB group order.
+    pub fn layouts(&self) -> &[Language] {
+        &self.layouts
+    }
+
+    /// Language of the active layout.
+    pub fn active_layout(&self) -> Option<Language> {
+        self.layouts.get(self.active).copied()
+    }
+
+    /// LED that shows the active group, if the group is tracked by a LED.
+    pub fn led(&self) -> Option<Led> {
+        match self.switch {
+            LayoutSwitch::Led(led) => Some(led),
+            LayoutSwitch::Keys(_) => None,
+        }
+    }
+
+    /// Update by the keyboard state after key events.
+    pub fn apply_state(&mut self, state: &KeyboardState) {
+        if !matches!(self.switch, LayoutSwitch::Keys(_)) {
+            return;
+        }
+        let pressed = state.contains(&self.switch_state);
+        if pressed && !self.switch_pressed {
+            self.next_layout();
+        }
+        self.switch_pressed = pressed;
+    }
+
+    /// Switch to the next layout as the switch keys do, e.g. after emulating them.
+    /// Layouts tracked by a LED are updated by the LED events.
+    pub fn next_layout(&mut self) {
+        if matches!(self.switch, LayoutSwitch::Keys(_)) && !self.layouts.is_empty() {
+            self.active = (self.active + 1) % self.layouts.len();
+        }
+    }
+
+    /// Keys that switch to the next layout, if the layout is switched by keys.
+    pub fn switch_keys(&self) -> Option<&[Key]> {
+        match &self.switch {
+            LayoutSwitch::Keys(keys) => Some(keys),
+            LayoutSwitch::Led(_) => None,
+        }
+    }
+
+    /// Update by the LED state change.
+    pub fn apply_led(&mut self, event: LedEvent) {
+        if self.led() == Some(event.led) {
+            self.active = usize::from(event.on);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::{KeyEvent, KeyPosition};
+
+    fn key_events(keys: &[Key], position: KeyPosition) -> Vec<KeyEvent> {
+        keys.iter().map(|&key| KeyEvent { key, position }).collect()
+    }
+
+    #[test]
+    fn switch_by_keys() {
+        let mut tracker = LayoutTracker::new(
+            vec![Language::En, Language::Ru],
+            LayoutSwitch::Keys(vec![Key::LAlt, Key::LShift]),
+        );
+        let mut state = KeyboardState::default();
+        assert_eq!(Some(Language::En), tracker.active_layout());
+
+        state.apply_events(&key_events(&[Key::LAlt], KeyPosition::Press));
+        tracker.apply_state(&state);
+        assert_eq!(Some(Language::En), tracker.active_layout());
+
+        state.apply_events(&key_events(&[Key::LShift, Key::A], KeyPosition::Press));
+        tracker.apply_state(&state);
+        assert_eq!(Some(Language::Ru), tracker.active_layout());
+
+        // Holding the combination does not switch the layout again.
+        state.apply_events(&key_events(&[Key::A], KeyPosition::Release));
+        tracker.apply_state(&state);
+        assert_eq!(Some(Language::Ru), tracker.active_layout());
+
+        state.apply_events(&key_events(&[Key::LShift], KeyPosition::Release));
+        tracker.apply_state(&state);
+        state.apply_events(&key_events(&[Key::LShift], KeyPosition::Press));
+        tracker.apply_state(&state);
+        assert_eq!(Some(Language::En), tracker.active_layout());
+    }
+
+    #[test]
+    fn switch_by_led() {
+        let mut tracker = LayoutTracker::new(
+            vec![Language::En, Language::Ru],
+            LayoutSwitch::Led(Led::ScrollLock),
+        );
+
+        tracker.apply_led(LedEvent {
+            led: Led::CapsLock,
+            on: true,
+        });
+        assert_eq!(Some(Language::En), tracker.active_layout());
+
+        tracker.apply_led(LedEvent {
+            led: Led::ScrollLock,
+            on: true,
+        });
+        assert_eq!(Some(Language::Ru), tracker.active_layout());
+    }
+}
@@ -1,13 +1,17 @@
 use evdev::{EventType, InputEvent, InputEventKind};
+use strum::{EnumCount, IntoEnumIterator};
 
 use super::KeyboardError;
 
-/// The keyboard state is expressed as a 128-bit bitmap.
-/// If the key number N is pressed, then the Nth element of the array has the value 1.
+/// Number of 64-bit words of the keyboard state bitmap.
+const STATE_WORDS: usize = Key::COUNT.div_ceil(64);
+
+/// The keyboard state is expressed as a bitmap of 64-bit words.
+/// If the key number N is pressed, then the Nth bit of the bitmap has the value 1.
 /// The key number is determined by the result of the `Key::number` function.
-#[derive(Default, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct KeyboardState {
-    value: u128,
+    value: [u64; STATE_WORDS],
 }
 
 impl KeyboardState {
@@ -26,21 +30,630 @@ impl KeyboardState {
         events.iter().for_each(|&event| self.apply_event(event));
     }
 
+    /// Whether no key is pressed.
+    pub fn is_empty(&self) -> bool {
+        self.value.iter().all(|&word| word == 0)
+    }
+
+    /// Whether the key is pressed.
+    pub fn is_pressed(&self, key: Key) -> bool {
+        let (word, bit) = key.position();
+        self.value[word] & bit != 0
+    }
+
+    /// Whether only modifier keys are pressed.
+    pub fn only_modifiers(&self) -> bool {
+        Key::iter()
+            .filter(|&key| self.is_pressed(key))
+            .all(Key::is_modifier)
+    }
+
+    /// Whether any of the keys pressed in the other state is also pressed in this one.
+    pub fn intersects(&self, other: &KeyboardState) -> bool {
+        self.value
+            .iter()
+            .zip(&other.value)
+            .any(|(word, other)| word & other != 0)
+    }
+
+    /// Whether all the keys pressed in the other state are also pressed in this one.
+    pub fn contains(&self, other: &KeyboardState) -> bool {
+        self.value
+            .iter()
+            .zip(&other.value)
+            .all(|(word, other)| word & other == *other)
+    }
+
     /// Apply the specified event to the state.
     fn apply_event(&mut self, event: KeyEvent) {
         let KeyEvent { key, position } = event;
+        let (word, bit) = key.position();
 
         match position {
             KeyPosition::Press => {
-                self.value |= 1_u128.rotate_left(key.number());
+                self.value[word] |= bit;
             }
             KeyPosition::Release => {
-                self.value &= (!1_u128).rotate_left(key.number());
+                self.value[word] &= !bit;
             }
         };
     }
 }
 
+/// Defines `Key` with its conversions from and to the evdev keys by the table of the evdev names,
+/// the keys after `;` have no evdev key.
+macro_rules! keys {
+    ($($key:ident => $code:ident,)* ; $($synthetic:ident,)*) => {
+        /// Keyboard key, the names are used in the binding configs.
+        #[derive(
+            Debug,
+            Copy,
+            Clone,
+            PartialEq,
+            Eq,
+            PartialOrd,
+            Ord,
+            Hash,
+            strum::EnumIter,
+            strum::EnumString,
+            strum::EnumCount,
+            strum::Display,
+        )]
+        #[repr(u16)]
+        pub enum Key {
+            $($key,)*
+            $($synthetic,)*
+        }
+
+        impl TryFrom<Key> for evdev::Key {
+            type Error = KeyboardError;
+            fn try_from(key: Key) -> Result<Self, Self::Error> {
+                let key = match key {
+                    $(Key::$key => evdev::Key::$code,)*
+                    $(Key::$synthetic)|* => return Err(KeyboardError::KeyNotSupported(key.to_string())),
+                };
+                Ok(key)
+            }
+        }
+
+        impl TryFrom<evdev::Key> for Key {
+            type Error = KeyboardError;
+            fn try_from(key: evdev::Key) -> Result<Self, Self::Error> {
+                let key = match key {
+                    $(evdev::Key::$code => Key::$key,)*
+                    key => return Err(KeyboardError::KeyNotSupported(key.code().to_string())),
+                };
+                Ok(key)
+            }
+        }
+    };
+}
+
+keys! {
+    A => KEY_A,
+    B => KEY_B,
+    C => KEY_C,
+    D => KEY_D,
+    E => KEY_E,
+    F => KEY_F,
+    G => KEY_G,
+    H => KEY_H,
+    I => KEY_I,
+    J => KEY_J,
+    K => KEY_K,
+    L => KEY_L,
+    M => KEY_M,
+    N => KEY_N,
+    O => KEY_O,
+    P => KEY_P,
+    Q => KEY_Q,
+    R => KEY_R,
+    S => KEY_S,
+    T => KEY_T,
+    U => KEY_U,
+    V => KEY_V,
+    W => KEY_W,
+    X => KEY_X,
+    Y => KEY_Y,
+    Z => KEY_Z,
+    K1 => KEY_1,
+    K2 => KEY_2,
+    K3 => KEY_3,
+    K4 => KEY_4,
+    K5 => KEY_5,
+    K6 => KEY_6,
+    K7 => KEY_7,
+    K8 => KEY_8,
+    K9 => KEY_9,
+    K0 => KEY_0,
+    Enter => KEY_ENTER,
+    Escape => KEY_ESC,
+    BackSpace => KEY_BACKSPACE,
+    Tab => KEY_TAB,
+    Space => KEY_SPACE,
+    Minus => KEY_MINUS,
+    Equal => KEY_EQUAL,
+    LBrace => KEY_LEFTBRACE,
+    RBrace => KEY_RIGHTBRACE,
+    Backslash => KEY_BACKSLASH,
+    Semicolon => KEY_SEMICOLON,
+    Apostrophe => KEY_APOSTROPHE,
+    Grave => KEY_GRAVE,
+    Comma => KEY_COMMA,
+    Dot => KEY_DOT,
+    Slash => KEY_SLASH,
+    CapsLock => KEY_CAPSLOCK,
+    LCtrl => KEY_LEFTCTRL,
+    LShift => KEY_LEFTSHIFT,
+    LAlt => KEY_LEFTALT,
+    LWin => KEY_LEFTMETA,
+    RCtrl => KEY_RIGHTCTRL,
+    RShift => KEY_RIGHTSHIFT,
+    RAlt => KEY_RIGHTALT,
+    RWin => KEY_RIGHTMETA,
+    F1 => KEY_F1,
+    F2 => KEY_F2,
+    F3 => KEY_F3,
+    F4 => KEY_F4,
+    F5 => KEY_F5,
+    F6 => KEY_F6,
+    F7 => KEY_F7,
+    F8 => KEY_F8,
+    F9 => KEY_F9,
+    F10 => KEY_F10,
+    F11 => KEY_F11,
+    F12 => KEY_F12,
+    PrintScreen => KEY_SYSRQ,
+    ScrollLock => KEY_SCROLLLOCK,
+    Pause => KEY_PAUSE,
+    Insert => KEY_INSERT,
+    Home => KEY_HOME,
+    PageUp => KEY_PAGEUP,
+    Delete => KEY_DELETE,
+    End => KEY_END,
+    PageDown => KEY_PAGEDOWN,
+    Right => KEY_RIGHT,
+    Left => KEY_LEFT,
+    Down => KEY_DOWN,
+    Up => KEY_UP,
+    NumLock => KEY_NUMLOCK,
+    Keypadslash => KEY_KPSLASH,
+    KeypadAsterisk => KEY_KPASTERISK,
+    KeypadMinus => KEY_KPMINUS,
+    KeypadPlus => KEY_KPPLUS,
+    KeypadEnter => KEY_KPENTER,
+    Keypad1 => KEY_KP1,
+    Keypad2 => KEY_KP2,
+    Keypad3 => KEY_KP3,
+    Keypad4 => KEY_KP4,
+    Keypad5 => KEY_KP5,
+    Keypad6 => KEY_KP6,
+    Keypad7 => KEY_KP7,
+    Keypad8 => KEY_KP8,
+    Keypad9 => KEY_KP9,
+    Keypad0 => KEY_KP0,
+    KeypadDot => KEY_KPDOT,
+
+    // Keyboard.
+    ZenkakuHankaku => KEY_ZENKAKUHANKAKU,
+    Key102nd => KEY_102ND,
+
+    // International and system.
+    Ro => KEY_RO,
+    Katakana => KEY_KATAKANA,
+    Hiragana => KEY_HIRAGANA,
+    Henkan => KEY_HENKAN,
+    KatakanaHiragana => KEY_KATAKANAHIRAGANA,
+    Muhenkan => KEY_MUHENKAN,
+    KeypadJpComma => KEY_KPJPCOMMA,
+    LineFeed => KEY_LINEFEED,
+    Macro => KEY_MACRO,
+    Mute => KEY_MUTE,
+    VolumeDown => KEY_VOLUMEDOWN,
+    VolumeUp => KEY_VOLUMEUP,
+    Power => KEY_POWER,
+    KeypadEqual => KEY_KPEQUAL,
+    KeypadPlusMinus => KEY_KPPLUSMINUS,
+    Scale => KEY_SCALE,
+    KeypadComma => KEY_KPCOMMA,
+    Hangeul => KEY_HANGEUL,
+    Hanja => KEY_HANJA,
+    Yen => KEY_YEN,
+    Compose => KEY_COMPOSE,
+
+    // Application and media.
+    Stop => KEY_STOP,
+    Again => KEY_AGAIN,
+    Props => KEY_PROPS,
+    Undo => KEY_UNDO,
+    Front => KEY_FRONT,
+    Copy => KEY_COPY,
+    Open => KEY_OPEN,
+    Paste => KEY_PASTE,
+    Find => KEY_FIND,
+    Cut => KEY_CUT,
+    Help => KEY_HELP,
+    Menu => KEY_MENU,
+    Calc => KEY_CALC,
+    Setup => KEY_SETUP,
+    Sleep => KEY_SLEEP,
+    Wakeup => KEY_WAKEUP,
+    File => KEY_FILE,
+    SendFile => KEY_SENDFILE,
+    DeleteFile => KEY_DELETEFILE,
+    Xfer => KEY_XFER,
+    Prog1 => KEY_PROG1,
+    Prog2 => KEY_PROG2,
+    Www => KEY_WWW,
+    MsDos => KEY_MSDOS,
+    Coffee => KEY_COFFEE,
+    Direction => KEY_DIRECTION,
+    CycleWindows => KEY_CYCLEWINDOWS,
+    Mail => KEY_MAIL,
+    Bookmarks => KEY_BOOKMARKS,
+    Computer => KEY_COMPUTER,
+    Back => KEY_BACK,
+    Forward => KEY_FORWARD,
+    CloseCd => KEY_CLOSECD,
+    EjectCd => KEY_EJECTCD,
+    EjectCloseCd => KEY_EJECTCLOSECD,
+    NextSong => KEY_NEXTSONG,
+    PlayPause => KEY_PLAYPAUSE,
+    PreviousSong => KEY_PREVIOUSSONG,
+    StopCd => KEY_STOPCD,
+    Record => KEY_RECORD,
+    Rewind => KEY_REWIND,
+    Phone => KEY_PHONE,
+    Iso => KEY_ISO,
+    Config => KEY_CONFIG,
+    HomePage => KEY_HOMEPAGE,
+    Refresh => KEY_REFRESH,
+    Exit => KEY_EXIT,
+    Move => KEY_MOVE,
+    Edit => KEY_EDIT,
+    ScrollUp => KEY_SCROLLUP,
+    ScrollDown => KEY_SCROLLDOWN,
+    KeypadLeftParen => KEY_KPLEFTPAREN,
+    KeypadRightParen => KEY_KPRIGHTPAREN,
+    New => KEY_NEW,
+    Redo => KEY_REDO,
+
+    // F13-F24.
+    F13 => KEY_F13,
+    F14 => KEY_F14,
+    F15 => KEY_F15,
+    F16 => KEY_F16,
+    F17 => KEY_F17,
+    F18 => KEY_F18,
+    F19 => KEY_F19,
+    F20 => KEY_F20,
+    F21 => KEY_F21,
+    F22 => KEY_F22,
+    F23 => KEY_F23,
+    F24 => KEY_F24,
+
+    // Media and hardware.
+    PlayCd => KEY_PLAYCD,
+    PauseCd => KEY_PAUSECD,
+    Prog3 => KEY_PROG3,
+    Prog4 => KEY_PROG4,
+    Dashboard => KEY_DASHBOARD,
+    Suspend => KEY_SUSPEND,
+    Close => KEY_CLOSE,
+    Play => KEY_PLAY,
+    FastForward => KEY_FASTFORWARD,
+    BassBoost => KEY_BASSBOOST,
+    Print => KEY_PRINT,
+    Hp => KEY_HP,
+    Camera => KEY_CAMERA,
+    Sound => KEY_SOUND,
+    Question => KEY_QUESTION,
+    Email => KEY_EMAIL,
+    Chat => KEY_CHAT,
+    Search => KEY_SEARCH,
+    Connect => KEY_CONNECT,
+    Finance => KEY_FINANCE,
+    Sport => KEY_SPORT,
+    Shop => KEY_SHOP,
+    AltErase => KEY_ALTERASE,
+    Cancel => KEY_CANCEL,
+    BrightnessDown => KEY_BRIGHTNESSDOWN,
+    BrightnessUp => KEY_BRIGHTNESSUP,
+    Media => KEY_MEDIA,
+    SwitchVideoMode => KEY_SWITCHVIDEOMODE,
+    KbdIllumToggle => KEY_KBDILLUMTOGGLE,
+    KbdIllumDown => KEY_KBDILLUMDOWN,
+    KbdIllumUp => KEY_KBDILLUMUP,
+    Send => KEY_SEND,
+    Reply => KEY_REPLY,
+    ForwardMail => KEY_FORWARDMAIL,
+    Save => KEY_SAVE,
+    Documents => KEY_DOCUMENTS,
+    Battery => KEY_BATTERY,
+    Bluetooth => KEY_BLUETOOTH,
+    Wlan => KEY_WLAN,
+    Uwb => KEY_UWB,
+    VideoNext => KEY_VIDEO_NEXT,
+    VideoPrev => KEY_VIDEO_PREV,
+    BrightnessCycle => KEY_BRIGHTNESS_CYCLE,
+    BrightnessAuto => KEY_BRIGHTNESS_AUTO,
+    DisplayOff => KEY_DISPLAY_OFF,
+    Wwan => KEY_WWAN,
+    RfKill => KEY_RFKILL,
+    MicMute => KEY_MICMUTE,
+
+    // Consumer and remote control.
+    KeyOk => KEY_OK,
+    Select => KEY_SELECT,
+    Goto => KEY_GOTO,
+    Clear => KEY_CLEAR,
+    Power2 => KEY_POWER2,
+    KeyOption => KEY_OPTION,
+    Info => KEY_INFO,
+    Time => KEY_TIME,
+    Vendor => KEY_VENDOR,
+    Archive => KEY_ARCHIVE,
+    Program => KEY_PROGRAM,
+    Channel => KEY_CHANNEL,
+    Favorites => KEY_FAVORITES,
+    Epg => KEY_EPG,
+    Pvr => KEY_PVR,
+    Mhp => KEY_MHP,
+    Language => KEY_LANGUAGE,
+    Title => KEY_TITLE,
+    Subtitle => KEY_SUBTITLE,
+    Angle => KEY_ANGLE,
+    Zoom => KEY_ZOOM,
+    Mode => KEY_MODE,
+    KeyKeyboard => KEY_KEYBOARD,
+    Screen => KEY_SCREEN,
+    Pc => KEY_PC,
+    Tv => KEY_TV,
+    Tv2 => KEY_TV2,
+    Vcr => KEY_VCR,
+    Vcr2 => KEY_VCR2,
+    Sat => KEY_SAT,
+    Sat2 => KEY_SAT2,
+    Cd => KEY_CD,
+    Tape => KEY_TAPE,
+    Radio => KEY_RADIO,
+    Tuner => KEY_TUNER,
+    Player => KEY_PLAYER,
+    Text => KEY_TEXT,
+    Dvd => KEY_DVD,
+    Aux => KEY_AUX,
+    Mp3 => KEY_MP3,
+    Audio => KEY_AUDIO,
+    Video => KEY_VIDEO,
+    Directory => KEY_DIRECTORY,
+    List => KEY_LIST,
+    Memo => KEY_MEMO,
+    Calendar => KEY_CALENDAR,
+    Red => KEY_RED,
+    Green => KEY_GREEN,
+    Yellow => KEY_YELLOW,
+    Blue => KEY_BLUE,
+    ChannelUp => KEY_CHANNELUP,
+    ChannelDown => KEY_CHANNELDOWN,
+    First => KEY_FIRST,
+    Last => KEY_LAST,
+    Ab => KEY_AB,
+    Next => KEY_NEXT,
+    Restart => KEY_RESTART,
+    Slow => KEY_SLOW,
+    Shuffle => KEY_SHUFFLE,
+    Break => KEY_BREAK,
+    Previous => KEY_PREVIOUS,
+    Digits => KEY_DIGITS,
+    Teen => KEY_TEEN,
+    Twen => KEY_TWEN,
+    VideoPhone => KEY_VIDEOPHONE,
+    Games => KEY_GAMES,
+    ZoomIn => KEY_ZOOMIN,
+    ZoomOut => KEY_ZOOMOUT,
+    ZoomReset => KEY_ZOOMRESET,
+    WordProcessor => KEY_WORDPROCESSOR,
+    Editor => KEY_EDITOR,
+    Spreadsheet => KEY_SPREADSHEET,
+    GraphicsEditor => KEY_GRAPHICSEDITOR,
+    Presentation => KEY_PRESENTATION,
+    Database => KEY_DATABASE,
+    News => KEY_NEWS,
+    VoiceMail => KEY_VOICEMAIL,
+    AddressBook => KEY_ADDRESSBOOK,
+    Messenger => KEY_MESSENGER,
+    DisplayToggle => KEY_DISPLAYTOGGLE,
+    SpellCheck => KEY_SPELLCHECK,
+    LogOff => KEY_LOGOFF,
+    Dollar => KEY_DOLLAR,
+    Euro => KEY_EURO,
+    FrameBack => KEY_FRAMEBACK,
+    FrameForward => KEY_FRAMEFORWARD,
+    ContextMenu => KEY_CONTEXT_MENU,
+    MediaRepeat => KEY_MEDIA_REPEAT,
+    Channels10Up => KEY_10CHANNELSUP,
+    Channels10Down => KEY_10CHANNELSDOWN,
+    Images => KEY_IMAGES,
+    DelEol => KEY_DEL_EOL,
+    DelEos => KEY_DEL_EOS,
+    InsLine => KEY_INS_LINE,
+    DelLine => KEY_DEL_LINE,
+    Fn => KEY_FN,
+    FnEsc => KEY_FN_ESC,
+    FnF1 => KEY_FN_F1,
+    FnF2 => KEY_FN_F2,
+    FnF3 => KEY_FN_F3,
+    FnF4 => KEY_FN_F4,
+    FnF5 => KEY_FN_F5,
+    FnF6 => KEY_FN_F6,
+    FnF7 => KEY_FN_F7,
+    FnF8 => KEY_FN_F8,
+    FnF9 => KEY_FN_F9,
+    FnF10 => KEY_FN_F10,
+    FnF11 => KEY_FN_F11,
+    FnF12 => KEY_FN_F12,
+    Fn1 => KEY_FN_1,
+    Fn2 => KEY_FN_2,
+    FnD => KEY_FN_D,
+    FnE => KEY_FN_E,
+    FnF => KEY_FN_F,
+    FnS => KEY_FN_S,
+    FnB => KEY_FN_B,
+    BrlDot1 => KEY_BRL_DOT1,
+    BrlDot2 => KEY_BRL_DOT2,
+    BrlDot3 => KEY_BRL_DOT3,
+    BrlDot4 => KEY_BRL_DOT4,
+    BrlDot5 => KEY_BRL_DOT5,
+    BrlDot6 => KEY_BRL_DOT6,
+    BrlDot7 => KEY_BRL_DOT7,
+    BrlDot8 => KEY_BRL_DOT8,
+    BrlDot9 => KEY_BRL_DOT9,
+    BrlDot10 => KEY_BRL_DOT10,
+
+    // Numeric, camera and assistant.
+    Numeric0 => KEY_NUMERIC_0,
+    Numeric1 => KEY_NUMERIC_1,
+    Numeric2 => KEY_NUMERIC_2,
+    Numeric3 => KEY_NUMERIC_3,
+    Numeric4 => KEY_NUMERIC_4,
+    Numeric5 => KEY_NUMERIC_5,
+    Numeric6 => KEY_NUMERIC_6,
+    Numeric7 => KEY_NUMERIC_7,
+    Numeric8 => KEY_NUMERIC_8,
+    Numeric9 => KEY_NUMERIC_9,
+    NumericStar => KEY_NUMERIC_STAR,
+    NumericPound => KEY_NUMERIC_POUND,
+    NumericA => KEY_NUMERIC_A,
+    NumericB => KEY_NUMERIC_B,
+    NumericC => KEY_NUMERIC_C,
+    NumericD => KEY_NUMERIC_D,
+    CameraFocus => KEY_CAMERA_FOCUS,
+    WpsButton => KEY_WPS_BUTTON,
+    TouchpadToggle => KEY_TOUCHPAD_TOGGLE,
+    TouchpadOn => KEY_TOUCHPAD_ON,
+    TouchpadOff => KEY_TOUCHPAD_OFF,
+    CameraZoomIn => KEY_CAMERA_ZOOMIN,
+    CameraZoomOut => KEY_CAMERA_ZOOMOUT,
+    CameraUp => KEY_CAMERA_UP,
+    CameraDown => KEY_CAMERA_DOWN,
+    CameraLeft => KEY_CAMERA_LEFT,
+    CameraRight => KEY_CAMERA_RIGHT,
+    AttendantOn => KEY_ATTENDANT_ON,
+    AttendantOff => KEY_ATTENDANT_OFF,
+    AttendantToggle => KEY_ATTENDANT_TOGGLE,
+    LightsToggle => KEY_LIGHTS_TOGGLE,
+    AlsToggle => KEY_ALS_TOGGLE,
+    ButtonConfig => KEY_BUTTONCONFIG,
+    TaskManager => KEY_TASKMANAGER,
+    Journal => KEY_JOURNAL,
+    ControlPanel => KEY_CONTROLPANEL,
+    AppSelect => KEY_APPSELECT,
+    ScreenSaver => KEY_SCREENSAVER,
+    VoiceCommand => KEY_VOICECOMMAND,
+    Assistant => KEY_ASSISTANT,
+    KbdLayoutNext => KEY_KBD_LAYOUT_NEXT,
+    BrightnessMin => KEY_BRIGHTNESS_MIN,
+    BrightnessMax => KEY_BRIGHTNESS_MAX,
+    KbdInputAssistPrev => KEY_KBDINPUTASSIST_PREV,
+    KbdInputAssistNext => KEY_KBDINPUTASSIST_NEXT,
+    KbdInputAssistPrevGroup => KEY_KBDINPUTASSIST_PREVGROUP,
+    KbdInputAssistNextGroup => KEY_KBDINPUTASSIST_NEXTGROUP,
+    KbdInputAssistAccept => KEY_KBDINPUTASSIST_ACCEPT,
+    KbdInputAssistCancel => KEY_KBDINPUTASSIST_CANCEL,
+    RightUp => KEY_RIGHT_UP,
+    RightDown => KEY_RIGHT_DOWN,
+    LeftUp => KEY_LEFT_UP,
+    LeftDown => KEY_LEFT_DOWN,
+    RootMenu => KEY_ROOT_MENU,
+    MediaTopMenu => KEY_MEDIA_TOP_MENU,
+    Numeric11 => KEY_NUMERIC_11,
+    Numeric12 => KEY_NUMERIC_12,
+    AudioDesc => KEY_AUDIO_DESC,
+    Mode3d => KEY_3D_MODE,
+    NextFavorite => KEY_NEXT_FAVORITE,
+    StopRecord => KEY_STOP_RECORD,
+    PauseRecord => KEY_PAUSE_RECORD,
+    Vod => KEY_VOD,
+    Unmute => KEY_UNMUTE,
+    FastReverse => KEY_FASTREVERSE,
+    SlowReverse => KEY_SLOWREVERSE,
+    Data => KEY_DATA,
+    OnscreenKeyboard => KEY_ONSCREEN_KEYBOARD,
+    PrivacyScreenToggle => KEY_PRIVACY_SCREEN_TOGGLE,
+    SelectiveScreenshot => KEY_SELECTIVE_SCREENSHOT,
+
+    // Mouse buttons.
+    MouseLeft => BTN_LEFT,
+    MouseRight => BTN_RIGHT,
+    MouseMiddle => BTN_MIDDLE,
+    MouseSide => BTN_SIDE,
+    MouseExtra => BTN_EXTRA,
+    MouseForward => BTN_FORWARD,
+    MouseBack => BTN_BACK,
+    MouseTask => BTN_TASK,
+    ;
+    // Mouse wheel directions, each wheel step is a press released right away.
+    WheelUp,
+    WheelDown,
+    WheelLeft,
+    WheelRight,
+}
+
+impl Key {
+    /// Whether the key is Ctrl, Shift, Alt or Win.
+    pub fn is_modifier(self) -> bool {
+        use Key::*;
+        matches!(
+            self,
+            LCtrl | LShift | LAlt | LWin | RCtrl | RShift | RAlt | RWin
+        )
+    }
+
+    /// Whether the key is a mouse button or a wheel direction.
+    pub fn is_pointer(self) -> bool {
+        use Key::*;
+        matches!(
+            self,
+            MouseLeft
+                | MouseRight
+                | MouseMiddle
+                | MouseSide
+                | MouseExtra
+                | MouseForward
+                | MouseBack
+                | MouseTask
+        ) || self.is_wheel()
+    }
+
+    /// Whether the key is a wheel direction.
+    pub fn is_wheel(self) -> bool {
+        use Key::*;
+        matches!(self, WheelUp | WheelDown | WheelLeft | WheelRight)
+    }
+
+    /// Wheel direction of the relative axis movement.
+    pub(crate) fn from_wheel(axis: evdev::RelativeAxisType, value: i32) -> Option<Key> {
+        use Key::*;
+        match (axis, value.signum()) {
+            (evdev::RelativeAxisType::REL_WHEEL, 1) => Some(WheelUp),
+            (evdev::RelativeAxisType::REL_WHEEL, -1) => Some(WheelDown),
+            (evdev::RelativeAxisType::REL_HWHEEL, 1) => Some(WheelRight),
+            (evdev::RelativeAxisType::REL_HWHEEL, -1) => Some(WheelLeft),
+            _ => None,
+        }
+    }
+
+    fn number(self) -> usize {
+        self as usize
+    }
+
+    /// Word of the keyboard state bitmap and the bit of the key in it.
+    fn position(self) -> (usize, u64) {
+        (self.number() / 64, 1 << (self.number() % 64))
+    }
+}
+
+/// Modifier that is pressed on either side of the keyboard.
 #[derive(
     Debug,
     Copy,
@@ -54,353 +667,62 @@ impl KeyboardState {
     strum::EnumString,
     strum::Display,
 )]
-#[repr(u8)]
-pub enum Key {
-    A = 0,
-    B,
-    C,
-    D,
-    E,
-    F,
-    G,
-    H,
-    I,
-    J,
-    K,
-    L,
-    M,
-    N,
-    O,
-    P,
-    Q,
-    R,
-    S,
-    T,
-    U,
-    V,
-    W,
-    X,
-    Y,
-    Z,
-
-    K1,
-    K2,
-    K3,
-    K4,
-    K5,
-    K6,
-    K7,
-    K8,
-    K9,
-    K0,
-
-    Enter,
-    Escape,
-    BackSpace,
-    Tab,
-    Space,
-    Minus,
-    Equal,
-    LBrace,
-    RBrace,
-    Backslash,
-    Semicolon,
-    Apostrophe,
-    Grave,
-    Comma,
-    Dot,
-    Slash,
-    CapsLock,
-    LCtrl,
-    LShift,
-    LAlt,
-    LWin,
-    RCtrl,
-    RShift,
-    RAlt,
-    RWin,
-
-    F1,
-    F2,
-    F3,
-    F4,
-    F5,
-    F6,
-    F7,
-    F8,
-    F9,
-    F10,
-    F11,
-    F12,
-    PrintScreen,
-    ScrollLock,
-    Pause,
-    Insert,
-    Home,
-    PageUp,
-    Delete,
-    End,
-    PageDown,
-    Right,
-    Left,
-    Down,
-    Up,
-
-    NumLock,
-    Keypadslash,
-    KeypadAsterisk,
-    KeypadMinus,
-    KeypadPlus,
-    KeypadEnter,
-    Keypad1,
-    Keypad2,
-    Keypad3,
-    Keypad4,
-    Keypad5,
-    Keypad6,
-    Keypad7,
-    Keypad8,
-    Keypad9,
-    Keypad0,
-    KeypadDot,
+pub enum Modifier {
+    Ctrl,
+    Shift,
+    Alt,
+    Super,
 }
 
-impl Key {
-    fn number(self) -> u32 {
-        self as u32
+impl Modifier {
+    /// Left and right keys of the modifier.
+    pub fn keys(self) -> [Key; 2] {
+        use Key::*;
+        match self {
+            Modifier::Ctrl => [LCtrl, RCtrl],
+            Modifier::Shift => [LShift, RShift],
+            Modifier::Alt => [LAlt, RAlt],
+            Modifier::Super => [LWin, RWin],
+        }
     }
 }
 
-impl From<Key> for evdev::Key {
+/// Key of a binding: an exact physical key or a modifier on either side.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BindingKey {
+    Key(Key),
+    Modifier(Modifier),
+}
+
+impl From<Key> for BindingKey {
     fn from(key: Key) -> Self {
-        use Key::*;
-        match key {
-            A => evdev::Key::KEY_A,
-            B => evdev::Key::KEY_B,
-            C => evdev::Key::KEY_C,
-            D => evdev::Key::KEY_D,
-            E => evdev::Key::KEY_E,
-            F => evdev::Key::KEY_F,
-            G => evdev::Key::KEY_G,
-            H => evdev::Key::KEY_H,
-            I => evdev::Key::KEY_I,
-            J => evdev::Key::KEY_J,
-            K => evdev::Key::KEY_K,
-            L => evdev::Key::KEY_L,
-            M => evdev::Key::KEY_M,
-            N => evdev::Key::KEY_N,
-            O => evdev::Key::KEY_O,
-            P => evdev::Key::KEY_P,
-            Q => evdev::Key::KEY_Q,
-            R => evdev::Key::KEY_R,
-            S => evdev::Key::KEY_S,
-            T => evdev::Key::KEY_T,
-            U => evdev::Key::KEY_U,
-            V => evdev::Key::KEY_V,
-            W => evdev::Key::KEY_W,
-            X => evdev::Key::KEY_X,
-            Y => evdev::Key::KEY_Y,
-            Z => evdev::Key::KEY_Z,
-
-            K1 => evdev::Key::KEY_1,
-            K2 => evdev::Key::KEY_2,
-            K3 => evdev::Key::KEY_3,
-            K4 => evdev::Key::KEY_4,
-            K5 => evdev::Key::KEY_5,
-            K6 => evdev::Key::KEY_6,
-            K7 => evdev::Key::KEY_7,
-            K8 => evdev::Key::KEY_8,
-            K9 => evdev::Key::KEY_9,
-            K0 => evdev::Key::KEY_0,
-
-            Enter => evdev::Key::KEY_ENTER,
-            Escape => evdev::Key::KEY_ESC,
-            BackSpace => evdev::Key::KEY_BACKSPACE,
-            Tab => evdev::Key::KEY_TAB,
-            Space => evdev::Key::KEY_SPACE,
-            Minus => evdev::Key::KEY_MINUS,
-            Equal => evdev::Key::KEY_EQUAL,
-            LBrace => evdev::Key::KEY_LEFTBRACE,
-            RBrace => evdev::Key::KEY_RIGHTBRACE,
-            Backslash => evdev::Key::KEY_BACKSLASH,
-            Semicolon => evdev::Key::KEY_SEMICOLON,
-            Apostrophe => evdev::Key::KEY_APOSTROPHE,
-            Grave => evdev::Key::KEY_GRAVE,
-            Comma => evdev::Key::KEY_COMMA,
-            Dot => evdev::Key::KEY_DOT,
-            Slash => evdev::Key::KEY_SLASH,
-            CapsLock => evdev::Key::KEY_CAPSLOCK,
-            LCtrl => evdev::Key::KEY_LEFTCTRL,
-            LShift => evdev::Key::KEY_LEFTSHIFT,
-            LAlt => evdev::Key::KEY_LEFTALT,
-            LWin => evdev::Key::KEY_LEFTMETA,
-            RCtrl => evdev::Key::KEY_RIGHTCTRL,
-            RShift => evdev::Key::KEY_RIGHTSHIFT,
-            RAlt => evdev::Key::KEY_RIGHTALT,
-            RWin => evdev::Key::KEY_RIGHTMETA,
-
-            F1 => evdev::Key::KEY_F1,
-            F2 => evdev::Key::KEY_F2,
-            F3 => evdev::Key::KEY_F3,
-            F4 => evdev::Key::KEY_F4,
-            F5 => evdev::Key::KEY_F5,
-            F6 => evdev::Key::KEY_F6,
-            F7 => evdev::Key::KEY_F7,
-            F8 => evdev::Key::KEY_F8,
-            F9 => evdev::Key::KEY_F9,
-            F10 => evdev::Key::KEY_F10,
-            F11 => evdev::Key::KEY_F11,
-            F12 => evdev::Key::KEY_F12,
-            PrintScreen => evdev::Key::KEY_SCREENSAVER,
-            ScrollLock => evdev::Key::KEY_SCROLLLOCK,
-            Pause => evdev::Key::KEY_PAUSE,
-            Insert => evdev::Key::KEY_INSERT,
-            Home => evdev::Key::KEY_HOME,
-            PageUp => evdev::Key::KEY_PAGEUP,
-            Delete => evdev::Key::KEY_DELETE,
-            End => evdev::Key::KEY_END,
-            PageDown => evdev::Key::KEY_PAGEDOWN,
-            Right => evdev::Key::KEY_RIGHT,
-            Left => evdev::Key::KEY_LEFT,
-            Down => evdev::Key::KEY_DOWN,
-            Up => evdev::Key::KEY_UP,
-
-            NumLock => evdev::Key::KEY_NUMLOCK,
-            Keypadslash => evdev::Key::KEY_KPSLASH,
-            KeypadAsterisk => evdev::Key::KEY_KPASTERISK,
-            KeypadMinus => evdev::Key::KEY_KPMINUS,
-            KeypadPlus => evdev::Key::KEY_KPPLUS,
-            KeypadEnter => evdev::Key::KEY_KPENTER,
-            Keypad1 => evdev::Key::KEY_KP1,
-            Keypad2 => evdev::Key::KEY_KP2,
-            Keypad3 => evdev::Key::KEY_KP3,
-            Keypad4 => evdev::Key::KEY_KP4,
-            Keypad5 => evdev::Key::KEY_KP5,
-            Keypad6 => evdev::Key::KEY_KP6,
-            Keypad7 => evdev::Key::KEY_KP7,
-            Keypad8 => evdev::Key::KEY_KP8,
-            Keypad9 => evdev::Key::KEY_KP9,
-            Keypad0 => evdev::Key::KEY_KP0,
-            KeypadDot => evdev::Key::KEY_KPDOT,
-        }
+        BindingKey::Key(key)
     }
 }
 
-impl TryFrom<evdev::Key> for Key {
-    type Error = KeyboardError;
-    fn try_from(key: evdev::Key) -> Result<Self, Self::Error> {
-        use Key::*;
-        let key = match key {
-            evdev::Key::KEY_A => A,
-            evdev::Key::KEY_B => B,
-            evdev::Key::KEY_C => C,
-            evdev::Key::KEY_D => D,
-            evdev::Key::KEY_E => E,
-            evdev::Key::KEY_F => F,
-            evdev::Key::KEY_G => G,
-            evdev::Key::KEY_H => H,
-            evdev::Key::KEY_I => I,
-            evdev::Key::KEY_J => J,
-            evdev::Key::KEY_K => K,
-            evdev::Key::KEY_L => L,
-            evdev::Key::KEY_M => M,
-            evdev::Key::KEY_N => N,
-            evdev::Key::KEY_O => O,
-            evdev::Key::KEY_P => P,
-            evdev::Key::KEY_Q => Q,
-            evdev::Key::KEY_R => R,
-            evdev::Key::KEY_S => S,
-            evdev::Key::KEY_T => T,
-            evdev::Key::KEY_U => U,
-            evdev::Key::KEY_V => V,
-            evdev::Key::KEY_W => W,
-            evdev::Key::KEY_X => X,
-            evdev::Key::KEY_Y => Y,
-            evdev::Key::KEY_Z => Z,
-
-            evdev::Key::KEY_1 => K1,
-            evdev::Key::KEY_2 => K2,
-            evdev::Key::KEY_3 => K3,
-            evdev::Key::KEY_4 => K4,
-            evdev::Key::KEY_5 => K5,
-            evdev::Key::KEY_6 => K6,
-            evdev::Key::KEY_7 => K7,
-            evdev::Key::KEY_8 => K8,
-            evdev::Key::KEY_9 => K9,
-            evdev::Key::KEY_0 => K0,
-
-            evdev::Key::KEY_ENTER => Enter,
-            evdev::Key::KEY_ESC => Escape,
-            evdev::Key::KEY_BACKSPACE => BackSpace,
-            evdev::Key::KEY_TAB => Tab,
-            evdev::Key::KEY_SPACE => Space,
-            evdev::Key::KEY_MINUS => Minus,
-            evdev::Key::KEY_EQUAL => Equal,
-            evdev::Key::KEY_LEFTBRACE => LBrace,
-            evdev::Key::KEY_RIGHTBRACE => RBrace,
-            evdev::Key::KEY_BACKSLASH => Backslash,
-            evdev::Key::KEY_SEMICOLON => Semicolon,
-            evdev::Key::KEY_APOSTROPHE => Apostrophe,
-            evdev::Key::KEY_GRAVE => Grave,
-            evdev::Key::KEY_COMMA => Comma,
-            evdev::Key::KEY_DOT => Dot,
-            evdev::Key::KEY_SLASH => Slash,
-            evdev::Key::KEY_CAPSLOCK => CapsLock,
-            evdev::Key::KEY_LEFTCTRL => LCtrl,
-            evdev::Key::KEY_LEFTSHIFT => LShift,
-            evdev::Key::KEY_LEFTALT => LAlt,
-            evdev::Key::KEY_LEFTMETA => LWin,
-            evdev::Key::KEY_RIGHTCTRL => RCtrl,
-            evdev::Key::KEY_RIGHTSHIFT => RShift,
-            evdev::Key::KEY_RIGHTALT => RAlt,
-            evdev::Key::KEY_RIGHTMETA => RWin,
-
-            evdev::Key::KEY_F1 => F1,
-            evdev::Key::KEY_F2 => F2,
-            evdev::Key::KEY_F3 => F3,
-            evdev::Key::KEY_F4 => F4,
-            evdev::Key::KEY_F5 => F5,
-            evdev::Key::KEY_F6 => F6,
-            evdev::Key::KEY_F7 => F7,
-            evdev::Key::KEY_F8 => F8,
-            evdev::Key::KEY_F9 => F9,
-            evdev::Key::KEY_F10 => F10,
-            evdev::Key::KEY_F11 => F11,
-            evdev::Key::KEY_F12 => F12,
-            evdev::Key::KEY_SCREENSAVER => PrintScreen,
-            evdev::Key::KEY_SCROLLLOCK => ScrollLock,
-            evdev::Key::KEY_PAUSE => Pause,
-            evdev::Key::KEY_INSERT => Insert,
-            evdev::Key::KEY_HOME => Home,
-            evdev::Key::KEY_PAGEUP => PageUp,
-            evdev::Key::KEY_DELETE => Delete,
-            evdev::Key::KEY_END => End,
-            evdev::Key::KEY_PAGEDOWN => PageDown,
-            evdev::Key::KEY_RIGHT => Right,
-            evdev::Key::KEY_LEFT => Left,
-            evdev::Key::KEY_DOWN => Down,
-            evdev::Key::KEY_UP => Up,
-
-            evdev::Key::KEY_NUMLOCK => NumLock,
-            evdev::Key::KEY_KPSLASH => Keypadslash,
-            evdev::Key::KEY_KPASTERISK => KeypadAsterisk,
-            evdev::Key::KEY_KPMINUS => KeypadMinus,
-            evdev::Key::KEY_KPPLUS => KeypadPlus,
-            evdev::Key::KEY_KPENTER => KeypadEnter,
-            evdev::Key::KEY_KP1 => Keypad1,
-            evdev::Key::KEY_KP2 => Keypad2,
-            evdev::Key::KEY_KP3 => Keypad3,
-            evdev::Key::KEY_KP4 => Keypad4,
-            evdev::Key::KEY_KP5 => Keypad5,
-            evdev::Key::KEY_KP6 => Keypad6,
-            evdev::Key::KEY_KP7 => Keypad7,
-            evdev::Key::KEY_KP8 => Keypad8,
-            evdev::Key::KEY_KP9 => Keypad9,
-            evdev::Key::KEY_KP0 => Keypad0,
-            evdev::Key::KEY_KPDOT => KeypadDot,
-            key => return Err(KeyboardError::KeyNotSupported(key.code().to_string())),
-        };
-        Ok(key)
+impl From<Modifier> for BindingKey {
+    fn from(modifier: Modifier) -> Self {
+        BindingKey::Modifier(modifier)
+    }
+}
+
+/// "Ctrl", "Shift", "Alt" and "Super" are modifiers on either side, other names are physical keys.
+impl std::str::FromStr for BindingKey {
+    type Err = strum::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Modifier::from_str(s)
+            .map(BindingKey::Modifier)
+            .or_else(|_| Key::from_str(s).map(BindingKey::Key))
+    }
+}
+
+impl std::fmt::Display for BindingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindingKey::Key(key) => write!(f, "{key}"),
+            BindingKey::Modifier(modifier) => write!(f, "{modifier}"),
+        }
     }
 }
 
@@ -441,14 +763,15 @@ pub struct KeyEvent {
     pub position: KeyPosition,
 }
 
-impl From<KeyEvent> for InputEvent {
-    fn from(event: KeyEvent) -> Self {
+impl TryFrom<KeyEvent> for InputEvent {
+    type Error = KeyboardError;
+    fn try_from(event: KeyEvent) -> Result<Self, Self::Error> {
         let KeyEvent { key, position } = event;
-        InputEvent::new(
+        Ok(InputEvent::new(
             EventType::KEY,
-            evdev::Key::from(key).code(),
+            evdev::Key::try_from(key)?.code(),
             i32::from(position),
-        )
+        ))
     }
 }
 
@@ -466,3 +789,122 @@ impl TryFrom<InputEvent> for KeyEvent {
         Ok(Self { key, position })
     }
 }
+
+/// Keyboard LED indicator.
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    strum::EnumIter,
+    strum::EnumString,
+    strum::Display,
+)]
+pub enum Led {
+    NumLock,
+    CapsLock,
+    ScrollLock,
+    Compose,
+    Kana,
+}
+
+impl From<Led> for evdev::LedType {
+    fn from(led: Led) -> Self {
+        use Led::*;
+        match led {
+            NumLock => evdev::LedType::LED_NUML,
+            CapsLock => evdev::LedType::LED_CAPSL,
+            ScrollLock => evdev::LedType::LED_SCROLLL,
+            Compose => evdev::LedType::LED_COMPOSE,
+            Kana => evdev::LedType::LED_KANA,
+        }
+    }
+}
+
+impl TryFrom<evdev::LedType> for Led {
+    type Error = KeyboardError;
+    fn try_from(led: evdev::LedType) -> Result<Self, Self::Error> {
+        use Led::*;
+        let led = match led {
+            evdev::LedType::LED_NUML => NumLock,
+            evdev::LedType::LED_CAPSL => CapsLock,
+            evdev::LedType::LED_SCROLLL => ScrollLock,
+            evdev::LedType::LED_COMPOSE => Compose,
+            evdev::LedType::LED_KANA => Kana,
+            led => return Err(KeyboardError::LedNotSupported(led.0.to_string())),
+        };
+        Ok(led)
+    }
+}
+
+/// Keyboard event about LED state change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LedEvent {
+    pub led: Led,
+    pub on: bool,
+}
+
+impl TryFrom<InputEvent> for LedEvent {
+    type Error = KeyboardError;
+    fn try_from(event: InputEvent) -> Result<Self, Self::Error> {
+        let led: Led = match event.kind() {
+            InputEventKind::Led(led) => led.try_into(),
+            kind => Err(KeyboardError::InputEventKindNotSupported(format!(
+                "{kind:?}"
+            ))),
+        }?;
+
+        Ok(Self {
+            led,
+            on: event.value() != 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evdev_keys() {
+        for key in Key::iter().filter(|key| !key.is_wheel()) {
+            let evdev_key = evdev::Key::try_from(key).unwrap();
+            assert_eq!(Some(key), Key::try_from(evdev_key).ok());
+        }
+        assert!(evdev::Key::try_from(Key::WheelUp).is_err());
+        assert_eq!(
+            Some(Key::Key102nd),
+            Key::try_from(evdev::Key::KEY_102ND).ok()
+        );
+        assert_eq!(
+            Some(Key::MouseMiddle),
+            Key::try_from(evdev::Key::BTN_MIDDLE).ok()
+        );
+        assert!(Key::try_from(evdev::Key::BTN_SOUTH).is_err());
+    }
+
+    #[test]
+    fn state_of_all_keys() {
+        let mut state = KeyboardState::default();
+        state.apply_keys(&[Key::LWin, Key::SelectiveScreenshot]);
+        assert!(state.is_pressed(Key::SelectiveScreenshot));
+        assert!(!state.is_pressed(Key::VolumeUp));
+        assert!(!state.only_modifiers());
+
+        let mut other = KeyboardState::default();
+        other.apply_keys(&[Key::SelectiveScreenshot]);
+        assert!(state.contains(&other));
+        assert!(state.intersects(&other));
+
+        state.apply_events(&[KeyEvent {
+            key: Key::SelectiveScreenshot,
+            position: KeyPosition::Release,
+        }]);
+        assert!(!state.intersects(&other));
+        assert!(state.only_modifiers());
+    }
+}
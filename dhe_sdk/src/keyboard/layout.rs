@@ -0,0 +1,417 @@
+use std::sync::LazyLock;
+
+use super::{Key, KeyboardError};
+use crate::language::{BigramScorer, Language, TextScorer};
+
+/// Keyboard layout: characters produced by physical keys without and with Shift.
+/// New layouts are described by their own tables, see `KeyboardLayout::new`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyboardLayout {
+    name: &'static str,
+    language: Language,
+    keys: &'static [(Key, char, char)],
+}
+
+/// Keystroke that types a character.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Keystroke {
+    /// Key of the layout and whether Shift is needed.
+    Key(Key, bool),
+    /// Character without a key in the layout, typed by its hex code after Ctrl+Shift+U.
+    /// The digits are typed by the keys of the layout too.
+    Unicode(Vec<Key>),
+}
+
+/// Built-in layouts.
+pub const LAYOUTS: &[KeyboardLayout] = &[QWERTY, JCUKEN];
+
+/// US English layout.
+pub const QWERTY: KeyboardLayout = KeyboardLayout::new(
+    "qwerty",
+    Language::En,
+    &[
+        (Key::Grave, '`', '~'),
+        (Key::K1, '1', '!'),
+        (Key::K2, '2', '@'),
+        (Key::K3, '3', '#'),
+        (Key::K4, '4', '$'),
+        (Key::K5, '5', '%'),
+        (Key::K6, '6', '^'),
+        (Key::K7, '7', '&'),
+        (Key::K8, '8', '*'),
+        (Key::K9, '9', '('),
+        (Key::K0, '0', ')'),
+        (Key::Minus, '-', '_'),
+        (Key::Equal, '=', '+'),
+        (Key::Q, 'q', 'Q'),
+        (Key::W, 'w', 'W'),
+        (Key::E, 'e', 'E'),
+        (Key::R, 'r', 'R'),
+        (Key::T, 't', 'T'),
+        (Key::Y, 'y', 'Y'),
+        (Key::U, 'u', 'U'),
+        (Key::I, 'i', 'I'),
+        (Key::O, 'o', 'O'),
+        (Key::P, 'p', 'P'),
+        (Key::LBrace, '[', '{'),
+        (Key::RBrace, ']', '}'),
+        (Key::Backslash, '\\', '|'),
+        (Key::A, 'a', 'A'),
+        (Key::S, 's', 'S'),
+        (Key::D, 'd', 'D'),
+        (Key::F, 'f', 'F'),
+        (Key::G, 'g', 'G'),
+        (Key::H, 'h', 'H'),
+        (Key::J, 'j', 'J'),
+        (Key::K, 'k', 'K'),
+        (Key::L, 'l', 'L'),
+        (Key::Semicolon, ';', ':'),
+        (Key::Apostrophe, '\'', '"'),
+        (Key::Z, 'z', 'Z'),
+        (Key::X, 'x', 'X'),
+        (Key::C, 'c', 'C'),
+        (Key::V, 'v', 'V'),
+        (Key::B, 'b', 'B'),
+        (Key::N, 'n', 'N'),
+        (Key::M, 'm', 'M'),
+        (Key::Comma, ',', '<'),
+        (Key::Dot, '.', '>'),
+        (Key::Slash, '/', '?'),
+        (Key::Space, ' ', ' '),
+        (Key::Tab, '\t', '\t'),
+        (Key::Enter, '\n', '\n'),
+    ],
+);
+
+/// Russian layout.
+pub const JCUKEN: KeyboardLayout = KeyboardLayout::new(
+    "jcuken",
+    Language::Ru,
+    &[
+        (Key::Grave, 'ё', 'Ё'),
+        (Key::K1, '1', '!'),
+        (Key::K2, '2', '"'),
+        (Key::K3, '3', '№'),
+        (Key::K4, '4', ';'),
+        (Key::K5, '5', '%'),
+        (Key::K6, '6', ':'),
+        (Key::K7, '7', '?'),
+        (Key::K8, '8', '*'),
+        (Key::K9, '9', '('),
+        (Key::K0, '0', ')'),
+        (Key::Minus, '-', '_'),
+        (Key::Equal, '=', '+'),
+        (Key::Q, 'й', 'Й'),
+        (Key::W, 'ц', 'Ц'),
+        (Key::E, 'у', 'У'),
+        (Key::R, 'к', 'К'),
+        (Key::T, 'е', 'Е'),
+        (Key::Y, 'н', 'Н'),
+        (Key::U, 'г', 'Г'),
+        (Key::I, 'ш', 'Ш'),
+        (Key::O, 'щ', 'Щ'),
+        (Key::P, 'з', 'З'),
+        (Key::LBrace, 'х', 'Х'),
+        (Key::RBrace, 'ъ', 'Ъ'),
+        (Key::Backslash, '\\', '/'),
+        (Key::A, 'ф', 'Ф'),
+        (Key::S, 'ы', 'Ы'),
+        (Key::D, 'в', 'В'),
+        (Key::F, 'а', 'А'),
+        (Key::G, 'п', 'П'),
+        (Key::H, 'р', 'Р'),
+        (Key::J, 'о', 'О'),
+        (Key::K, 'л', 'Л'),
+        (Key::L, 'д', 'Д'),
+        (Key::Semicolon, 'ж', 'Ж'),
+        (Key::Apostrophe, 'э', 'Э'),
+        (Key::Z, 'я', 'Я'),
+        (Key::X, 'ч', 'Ч'),
+        (Key::C, 'с', 'С'),
+        (Key::V, 'м', 'М'),
+        (Key::B, 'и', 'И'),
+        (Key::N, 'т', 'Т'),
+        (Key::M, 'ь', 'Ь'),
+        (Key::Comma, 'б', 'Б'),
+        (Key::Dot, 'ю', 'Ю'),
+        (Key::Slash, '.', ','),
+        (Key::Space, ' ', ' '),
+        (Key::Tab, '\t', '\t'),
+        (Key::Enter, '\n', '\n'),
+    ],
+);
+
+impl KeyboardLayout {
+    /// Layout by the table of keys with the characters they produce without and with Shift.
+    pub const fn new(
+        name: &'static str,
+        language: Language,
+        keys: &'static [(Key, char, char)],
+    ) -> Self {
+        Self {
+            name,
+            language,
+            keys,
+        }
+    }
+
+    /// Built-in layout of the given language.
+    pub fn for_language(language: Language) -> Option<&'static KeyboardLayout> {
+        LAYOUTS.iter().find(|layout| layout.language == language)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// Key and whether Shift is needed to type the character.
+    pub fn key_of(&self, c: char) -> Option<(Key, bool)> {
+        self.keys.iter().find_map(|&(key, plain, shifted)| {
+            if c == plain {
+                Some((key, false))
+            } else if c == shifted {
+                Some((key, true))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Character typed by the key with or without Shift.
+    pub fn char_of(&self, key: Key, shift: bool) -> Option<char> {
+        self.keys
+            .iter()
+            .find(|(k, _, _)| *k == key)
+            .map(|&(_, plain, shifted)| if shift { shifted } else { plain })
+    }
+
+    /// Keys of the hex digits of the character code, `None` if the layout has no keys
+    /// for some of them, e.g. a-f in a Cyrillic layout.
+    pub fn code_keys(&self, c: char) -> Option<Vec<Key>> {
+        format!("{:x}", c as u32)
+            .chars()
+            .map(|digit| match self.key_of(digit) {
+                Some((key, false)) => Some(key),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Keystrokes that type the text in this layout. A character without a key can't be typed
+    /// if the layout has no keys for the hex digits of its code.
+    pub fn keystrokes(&self, text: &str) -> Result<Vec<Keystroke>, KeyboardError> {
+        text.chars()
+            .map(|c| match self.key_of(c) {
+                Some((key, shift)) => Ok(Keystroke::Key(key, shift)),
+                None => self
+                    .code_keys(c)
+                    .map(Keystroke::Unicode)
+                    .ok_or(KeyboardError::CharNotSupported(c, self.name)),
+            })
+            .collect()
+    }
+
+    /// Text that would have been typed by the same keys in the other layout.
+    /// Characters without a key in this layout are kept as is.
+    pub fn remap(&self, text: &str, to: &KeyboardLayout) -> String {
+        text.chars()
+            .map(|c| {
+                self.key_of(c)
+                    .and_then(|(key, shift)| to.char_of(key, shift))
+                    .unwrap_or(c)
+            })
+            .collect()
+    }
+}
+
+/// Minimum score of a text retyped in another layout.
+const MIN_FIXED_SCORE: f64 = 0.6;
+/// Minimum gain of the score over the text as it is typed.
+const MIN_FIX_GAIN: f64 = 0.3;
+
+/// Fixer of text typed in a wrong layout, e.g. "ghbdtn" for "привет". Each layout comes with
+/// a scorer of its language that tells a real text from the keys typed in another layout.
+pub struct LayoutFixer {
+    layouts: Vec<(KeyboardLayout, Box<dyn TextScorer>)>,
+}
+
+impl LayoutFixer {
+    /// Fixer of the built-in layouts with the bigram scorers of their languages.
+    pub fn new() -> Self {
+        let mut fixer = Self { layouts: vec![] };
+        for layout in LAYOUTS {
+            if let Some(scorer) = BigramScorer::for_language(layout.language()) {
+                fixer.add_layout(*layout, scorer);
+            }
+        }
+        fixer
+    }
+
+    /// Add a layout with the scorer of the texts typed in it.
+    pub fn add_layout(&mut self, layout: KeyboardLayout, scorer: impl TextScorer + 'static) {
+        self.layouts.push((layout, Box::new(scorer)));
+    }
+
+    /// Text retyped in another layout if it was clearly typed in a wrong one. The text as it is
+    /// typed and each of its remaps are scored, `None` if no remap is clearly more like
+    /// a real text.
+    pub fn fix(&self, text: &str) -> Option<String> {
+        // The typed text is scored in the language it is the most like, whatever its layout.
+        let typed_score = self
+            .layouts
+            .iter()
+            .filter_map(|(_, scorer)| scorer.score(text))
+            .max_by(f64::total_cmp)?;
+        self.layouts
+            .iter()
+            .flat_map(|from| self.layouts.iter().map(move |to| (from, to)))
+            .filter(|((from, _), (to, _))| from != to)
+            .filter_map(|((from, _), (to, scorer))| {
+                let fixed = from.remap(text, to);
+                // Letters typed as punctuation are fixed, but not the other way round.
+                if fixed == text || letters(&fixed) < letters(text) {
+                    return None;
+                }
+                let score = scorer.score(&fixed)?;
+                (score >= MIN_FIXED_SCORE && score - typed_score >= MIN_FIX_GAIN)
+                    .then_some((score, fixed))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, fixed)| fixed)
+    }
+}
+
+impl Default for LayoutFixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Text retyped by the fixer of the built-in layouts, see `LayoutFixer::fix`.
+pub fn fix_layout(text: &str) -> Option<String> {
+    static FIXER: LazyLock<LayoutFixer> = LazyLock::new(LayoutFixer::new);
+    FIXER.fix(text)
+}
+
+fn letters(text: &str) -> usize {
+    text.chars().filter(|c| c.is_alphabetic()).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remap() {
+        assert_eq!("Привет, мир!", QWERTY.remap("Ghbdtn? vbh!", &JCUKEN));
+        assert_eq!("Hello, world!", JCUKEN.remap("Руддщб цщкдв!", &QWERTY));
+        assert_eq!("ёжик", QWERTY.remap("`;br", &JCUKEN));
+    }
+
+    #[test]
+    fn remap_unknown_characters() {
+        assert_eq!("éпривет", QWERTY.remap("éghbdtn", &JCUKEN));
+    }
+
+    #[test]
+    fn key_of() {
+        assert_eq!(Some((Key::Q, true)), QWERTY.key_of('Q'));
+        assert_eq!(Some((Key::Slash, false)), JCUKEN.key_of('.'));
+        assert_eq!(None, JCUKEN.key_of('q'));
+        assert_eq!(
+            Some(Language::Ru),
+            KeyboardLayout::for_language(Language::Ru).map(|l| l.language())
+        );
+    }
+
+    #[test]
+    fn fix_wrong_layout() {
+        assert_eq!(Some("привет".to_string()), fix_layout("ghbdtn"));
+        assert_eq!(Some("Привет, мир!".to_string()), fix_layout("Ghbdtn? vbh!"));
+        assert_eq!(Some("hello".to_string()), fix_layout("руддщ"));
+        assert_eq!(Some("нет".to_string()), fix_layout("ytn"));
+        assert_eq!(
+            Some("the quick brown fox".to_string()),
+            fix_layout("еру йгшсл икщцт ащч")
+        );
+        assert_eq!(
+            Some("раскладка клавиатуры".to_string()),
+            fix_layout("hfcrkflrf rkfdbfnehs")
+        );
+    }
+
+    #[test]
+    fn keep_right_layout() {
+        for text in [
+            "привет",
+            "hello",
+            "the quick brown fox",
+            "раскладка клавиатуры",
+            "Привет, мир!",
+            "keyboard layout",
+            "что",
+            "yes",
+            "123",
+            "a",
+        ] {
+            assert_eq!(None, fix_layout(text), "{text}");
+        }
+    }
+
+    #[test]
+    fn fix_with_scorer() {
+        /// Scorer of the known words only.
+        struct Words(&'static [&'static str]);
+
+        impl TextScorer for Words {
+            fn score(&self, text: &str) -> Option<f64> {
+                Some(if self.0.contains(&text) { 1.0 } else { 0.0 })
+            }
+        }
+
+        let mut fixer = LayoutFixer { layouts: vec![] };
+        fixer.add_layout(QWERTY, Words(&["hello"]));
+        fixer.add_layout(JCUKEN, Words(&["да"]));
+        assert_eq!(Some("да".to_string()), fixer.fix("lf"));
+        assert_eq!(Some("hello".to_string()), fixer.fix("руддщ"));
+        assert_eq!(None, fixer.fix("ghbdtn"));
+        assert_eq!(None, fixer.fix("hello"));
+    }
+
+    #[test]
+    fn keystrokes() {
+        use Key::*;
+        assert_eq!(
+            vec![Keystroke::Key(G, true), Keystroke::Key(Slash, false),],
+            JCUKEN.keystrokes("П.").unwrap()
+        );
+        assert_eq!(
+            vec![
+                Keystroke::Unicode(vec![K4, K1, F]),
+                Keystroke::Unicode(vec![K2, K0, A, C]),
+            ],
+            QWERTY.keystrokes("П€").unwrap()
+        );
+        // The hex digits a-f have no keys in the Cyrillic layout.
+        assert!(matches!(
+            JCUKEN.keystrokes("€"),
+            Err(KeyboardError::CharNotSupported('€', _))
+        ));
+        assert_eq!(Some(vec![A, B]), QWERTY.code_keys('«'));
+        assert_eq!(None, JCUKEN.code_keys('«'));
+    }
+
+    #[test]
+    fn tables_are_consistent() {
+        for layout in LAYOUTS {
+            for &(key, plain, shifted) in layout.keys {
+                assert_eq!(Some(plain), layout.char_of(key, false));
+                assert_eq!(Some(shifted), layout.char_of(key, true));
+            }
+        }
+    }
+}
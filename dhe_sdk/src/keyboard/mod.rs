@@ -1,17 +1,37 @@
+pub mod filter;
+pub mod group;
 pub mod keys;
+pub mod layout;
+pub mod sequence;
+pub mod word;
 
+pub use filter::*;
+pub use group::*;
 pub use keys::*;
+pub use layout::*;
+pub use sequence::*;
+pub use word::*;
 
-use std::{collections::HashMap, io, os::fd::AsRawFd};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    future, io, mem,
+    path::{Path, PathBuf},
+    task::Poll,
+    time::{Duration, SystemTime},
+};
 
 use evdev::{
     uinput::{VirtualDevice, VirtualDeviceBuilder},
-    AttributeSet, Device, InputEvent,
+    AttributeSet, Device, EventStream, InputEvent,
 };
-use mio::{unix::SourceFd, Events, Interest, Poll, Token};
+use futures_util::{stream, Stream};
+use inotify::{EventMask, Inotify, WatchMask};
 use strum::IntoEnumIterator;
 use thiserror::Error;
-use tracing::{debug, error, info};
+use tokio::{io::unix::AsyncFd, time::sleep};
+use tracing::{debug, error, info, warn};
+
+use crate::language::Language;
 
 #[derive(Error, Debug)]
 pub enum KeyboardError {
@@ -21,138 +41,745 @@ pub enum KeyboardError {
     KeyboardNotFound,
     #[error("key \"{0}\" not supported")]
     KeyNotSupported(String),
+    #[error("character \"{0}\" can't be typed in the {1} layout")]
+    CharNotSupported(char, &'static str),
     #[error("key position \"{0}\" not supported")]
     KeyPositionNotSupported(String),
     #[error("input event kind \"{0}\" not supported")]
     InputEventKindNotSupported(String),
+    #[error("led \"{0}\" not supported")]
+    LedNotSupported(String),
+    #[error("wrong key sequence \"{0}\": {1}")]
+    WrongKeySequence(String, String),
 }
 
+/// Name of the virtual device of `KeyboardEmulator`.
+pub const EMULATOR_DEVICE_NAME: &str = "dhe_keyboard";
+/// Name of the virtual device that forwards the events of grabbed keyboards.
+pub const FORWARD_DEVICE_NAME: &str = "dhe_forward";
+
 /// Keyboard listener helps to bind some event to keyboard shortcuts.
-pub struct KeyboardListener {
+pub struct KeyboardListener<A = String> {
     kr: KeyboardReader,
     state: KeyboardState,
-    actions: HashMap<KeyboardState, String>,
+    actions: SequenceMatcher<A>,
+    layout_tracker: Option<LayoutTracker>,
+    last_events: Vec<KeyEvent>,
+    /// Wheel steps are pressed and released by one event, the release is applied by the next call.
+    wheel_pressed: Vec<Key>,
 }
 
-impl KeyboardListener {
+impl<A> KeyboardListener<A> {
+    /// The listener has to be created inside a tokio runtime.
     pub fn new() -> Result<Self, KeyboardError> {
-        let kr = KeyboardReader::new()?;
+        Self::with_filter(DeviceFilter::default())
+    }
+
+    /// Listener of the keyboards chosen by the filter.
+    pub fn with_filter(filter: DeviceFilter) -> Result<Self, KeyboardError> {
+        let kr = KeyboardReader::new(filter)?;
         Ok(Self {
             kr,
             state: KeyboardState::default(),
-            actions: HashMap::default(),
+            actions: SequenceMatcher::new(DEFAULT_SEQUENCE_TIMEOUT),
+            layout_tracker: None,
+            last_events: vec![],
+            wheel_pressed: vec![],
         })
     }
 
     /// Bind an event to given keyboard shortcuts.
-    pub fn register_action<S: Into<String>>(&mut self, action: S, keys: &[Key]) {
-        let mut state = KeyboardState::default();
-        state.apply_keys(keys);
-        self.actions.insert(state, action.into());
+    pub fn register_action<T: Into<A>>(&mut self, action: T, keys: &[Key]) {
+        self.register_sequence(
+            action,
+            &KeySequence::from(keys.to_vec()),
+            Trigger::Press,
+            false,
+        );
+    }
+
+    /// Bind an event to the key combinations pressed one after another,
+    /// the trigger sets when the event happens after the last combination.
+    /// Keys of a grabbed sequence don't reach other applications while the keyboards are grabbed.
+    pub fn register_sequence<T: Into<A>>(
+        &mut self,
+        action: T,
+        sequence: &KeySequence,
+        trigger: Trigger,
+        grab: bool,
+    ) {
+        self.actions
+            .register(sequence, trigger, grab, action.into());
+    }
+
+    /// Remove all the bound events, e.g. to bind the ones of a reloaded configuration.
+    pub fn clear_actions(&mut self) {
+        self.actions.clear();
+    }
+
+    /// Read mouse buttons and wheels too, for bindings with mouse keys.
+    pub fn read_pointers(&mut self) -> Result<(), KeyboardError> {
+        self.kr.read_pointers()
+    }
+
+    /// Grab the keyboards exclusively: their events are forwarded through a virtual device,
+    /// except the key presses of grabbed sequences (modifiers are always forwarded).
+    pub fn grab(&mut self) -> Result<(), KeyboardError> {
+        self.kr.grab()
+    }
+
+    /// Set the maximum time between the steps of a sequence.
+    pub fn set_sequence_timeout(&mut self, timeout: Duration) {
+        self.actions.set_timeout(timeout);
+    }
+
+    /// Forget the pending steps of a sequence.
+    pub fn cancel_sequence(&mut self) {
+        self.actions.cancel();
+    }
+
+    /// Track the active keyboard layout, the initial LED state is read from the devices.
+    pub fn set_layout_tracker(&mut self, mut tracker: LayoutTracker) {
+        if let Some(led) = tracker.led() {
+            let on = self.kr.led_state(led);
+            tracker.apply_led(LedEvent { led, on });
+        }
+        self.layout_tracker = Some(tracker);
+    }
+
+    /// Language of the active keyboard layout, if the layout is tracked.
+    pub fn active_layout(&self) -> Option<Language> {
+        self.layout_tracker
+            .as_ref()
+            .and_then(LayoutTracker::active_layout)
+    }
+
+    /// Notify the layout tracker that the layout was switched by emulated keys.
+    pub fn layout_switched(&mut self) {
+        if let Some(tracker) = &mut self.layout_tracker {
+            tracker.next_layout();
+        }
+    }
+
+    /// Whether all the keys are released.
+    pub fn is_released(&self) -> bool {
+        self.state.is_empty()
+    }
+
+    /// Key events read by the last `get_action` call.
+    pub fn last_events(&self) -> &[KeyEvent] {
+        &self.last_events
     }
 
     /// Request an event that happened.
-    pub fn get_action(&mut self) -> Result<Option<&str>, KeyboardError> {
-        let events = self.kr.read()?;
-        self.state.apply_events(&events);
-        let action = self.actions.get(&self.state).map(|s| s.as_ref());
-        Ok(action)
+    /// Waits for a report of key events or for the moment a long press fires,
+    /// the wait is cancelled by dropping the future without losing the events read so far.
+    pub async fn get_action(&mut self) -> Result<Option<&A>, KeyboardError> {
+        let events = if self.wheel_pressed.is_empty() {
+            self.kr.read(self.actions.deadline()).await?
+        } else {
+            let now = SystemTime::now();
+            self.wheel_pressed
+                .drain(..)
+                .map(|key| {
+                    let event = KeyEvent {
+                        key,
+                        position: KeyPosition::Release,
+                    };
+                    DeviceEvent::Key(event, now)
+                })
+                .collect()
+        };
+        let mut time = SystemTime::now();
+        let mut key_events = vec![];
+        for event in events {
+            match event {
+                DeviceEvent::Key(event, event_time) => {
+                    key_events.push(event);
+                    time = event_time;
+                }
+                DeviceEvent::Led(event) => {
+                    if let Some(tracker) = &mut self.layout_tracker {
+                        tracker.apply_led(event);
+                    }
+                }
+            }
+        }
+        self.wheel_pressed = key_events
+            .iter()
+            .filter(|event| event.key.is_wheel() && event.position == KeyPosition::Press)
+            .map(|event| event.key)
+            .collect();
+        self.state.apply_events(&key_events);
+        if let Some(tracker) = &mut self.layout_tracker {
+            tracker.apply_state(&self.state);
+        }
+        let fired = self.actions.apply(&key_events, &self.state, time).is_some();
+        let swallowed: Vec<Key> = if self.actions.consumed() {
+            key_events
+                .iter()
+                .filter(|event| event.position == KeyPosition::Press && !event.key.is_modifier())
+                .map(|event| event.key)
+                .collect()
+        } else {
+            vec![]
+        };
+        self.kr.forward(&swallowed)?;
+        self.last_events = key_events;
+        Ok(if fired { self.actions.fired() } else { None })
+    }
+
+    /// Stream of the events that happen, reading stops when the stream is dropped.
+    pub fn actions(&mut self) -> impl Stream<Item = Result<A, KeyboardError>> + '_
+    where
+        A: Clone,
+    {
+        stream::unfold(self, |listener| async move {
+            let action = loop {
+                match listener.get_action().await {
+                    Ok(Some(action)) => break Ok(action.clone()),
+                    Ok(None) => continue,
+                    Err(err) => break Err(err),
+                }
+            };
+            Some((action, listener))
+        })
     }
 }
 
+/// Input event of a keyboard or pointer device.
+enum DeviceEvent {
+    /// Key event with the time it happened.
+    Key(KeyEvent, SystemTime),
+    Led(LedEvent),
+}
+
+/// Directory of the input device nodes, watched for plugged and unplugged keyboards.
+const INPUT_DIR: &str = "/dev/input";
+
 /// Keyboard event reader.
 struct KeyboardReader {
-    poll: Poll,
-    events: Events,
-    devices: HashMap<Token, Device>,
+    watcher: AsyncFd<Inotify>,
+    filter: DeviceFilter,
+    devices: HashMap<usize, EventStream>,
+    /// Pointer devices among the devices, they are never grabbed.
+    pointers: HashSet<usize>,
+    /// Grabbed devices whose key events are forwarded.
+    grabbed: HashSet<usize>,
+    read_pointers: bool,
+    paths: HashMap<PathBuf, usize>,
+    next_id: usize,
+    forwarder: Option<Forwarder>,
+    /// Events of the report being read, kept by the reader so that a cancelled read loses nothing.
+    partial: Vec<DeviceEvent>,
+    /// Read reports that are not returned yet.
+    reports: VecDeque<Vec<DeviceEvent>>,
 }
 
 impl KeyboardReader {
-    fn new() -> Result<Self, KeyboardError> {
-        let devices_iter = evdev::enumerate().filter(|device| {
-            device
-                .1
-                .supported_keys()
-                .map_or(false, |keys| keys.contains(evdev::Key::KEY_ENTER))
+    /// The reader registers its descriptors in the tokio runtime it is created in.
+    fn new(filter: DeviceFilter) -> Result<Self, KeyboardError> {
+        let watcher =
+            Inotify::init().map_err(|err| KeyboardError::Io("init inotify".to_string(), err))?;
+        watcher
+            .watches()
+            .add(
+                INPUT_DIR,
+                WatchMask::CREATE | WatchMask::ATTRIB | WatchMask::DELETE,
+            )
+            .map_err(|err| KeyboardError::Io(format!("watch {INPUT_DIR}"), err))?;
+        let watcher = AsyncFd::new(watcher)
+            .map_err(|err| KeyboardError::Io("register inotify".to_string(), err))?;
+
+        let mut reader = Self {
+            watcher,
+            filter,
+            devices: HashMap::new(),
+            pointers: HashSet::new(),
+            grabbed: HashSet::new(),
+            read_pointers: false,
+            paths: HashMap::new(),
+            next_id: 0,
+            forwarder: None,
+            partial: vec![],
+            reports: VecDeque::new(),
+        };
+        for (path, device) in evdev::enumerate() {
+            reader.add_device(path, device)?;
+        }
+
+        if reader.devices.is_empty() {
+            return Err(KeyboardError::KeyboardNotFound);
+        }
+        Ok(reader)
+    }
+
+    /// Start reading pointer devices too.
+    fn read_pointers(&mut self) -> Result<(), KeyboardError> {
+        if self.read_pointers {
+            return Ok(());
+        }
+        self.read_pointers = true;
+        for (path, device) in evdev::enumerate() {
+            self.add_device(path, device)?;
+        }
+        Ok(())
+    }
+
+    /// Start reading the device if it is a keyboard or a pointer chosen by the filter.
+    fn add_device(&mut self, path: PathBuf, mut device: Device) -> Result<(), KeyboardError> {
+        let Some(kind) = device_kind(&device) else {
+            return Ok(());
+        };
+        if self.paths.contains_key(&path) || (kind == DeviceKind::Pointer && !self.read_pointers) {
+            return Ok(());
+        }
+        if !self.filter.matches(&DeviceInfo::new(&path, &device, kind)) {
+            info!("skipped {kind} {:?} {:?}", device.name(), path);
+            return Ok(());
+        }
+        info!("found {kind} {:?} {:?}", device.name(), path);
+        let id = self.next_id;
+        if let Some(forwarder) = self
+            .forwarder
+            .as_ref()
+            .filter(|_| kind == DeviceKind::Keyboard)
+        {
+            if forwarder.covers(&device) {
+                device
+                    .grab()
+                    .map_err(|err| KeyboardError::Io("grab device".to_string(), err))?;
+                self.grabbed.insert(id);
+            } else {
+                warn!("device {path:?} is not grabbed, it has keys that can't be forwarded");
+            }
+        }
+        let stream = device
+            .into_event_stream()
+            .map_err(|err| KeyboardError::Io("register device".to_string(), err))?;
+        self.next_id += 1;
+        self.devices.insert(id, stream);
+        if kind == DeviceKind::Pointer {
+            self.pointers.insert(id);
+        }
+        self.paths.insert(path, id);
+        Ok(())
+    }
+
+    /// Stop reading the device, the keys held on it are released.
+    fn remove_device(&mut self, id: usize) -> Vec<DeviceEvent> {
+        let Some(stream) = self.devices.remove(&id) else {
+            return vec![];
+        };
+        let device = stream.device();
+        self.pointers.remove(&id);
+        let grabbed = self.grabbed.remove(&id);
+        self.paths.retain(|path, i| {
+            if *i == id {
+                info!("removed device {:?} {:?}", device.name(), path);
+            }
+            *i != id
         });
 
-        let poll =
-            Poll::new().map_err(|err| KeyboardError::Io("creating pool".to_string(), err))?;
-        let mut device_map = HashMap::new();
+        let held = device
+            .cached_state()
+            .key_vals()
+            .map(|keys| keys.iter().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let now = SystemTime::now();
+        held.into_iter()
+            .filter_map(|key| {
+                if let Some(forwarder) = self.forwarder.as_mut().filter(|_| grabbed) {
+                    forwarder.push(InputEvent::new(evdev::EventType::KEY, key.code(), 0));
+                }
+                let key = Key::try_from(key).ok()?;
+                let event = KeyEvent {
+                    key,
+                    position: KeyPosition::Release,
+                };
+                Some(DeviceEvent::Key(event, now))
+            })
+            .collect()
+    }
 
-        for (token_counter, (path, device)) in devices_iter.enumerate() {
-            info!("found device {:?} {:?}", device.name(), path);
-            let token = Token(token_counter);
-            poll.registry()
-                .register(
-                    &mut SourceFd(&device.as_raw_fd()),
-                    token,
-                    Interest::READABLE,
-                )
-                .map_err(|err| KeyboardError::Io("register device".to_string(), err))?;
-            device_map.insert(token, device);
+    /// Apply the changes of the input directory, returns the releases of the keys
+    /// held on the unplugged devices.
+    fn apply_hotplug(&mut self, changes: Vec<(EventMask, PathBuf)>) -> Vec<DeviceEvent> {
+        let mut events = vec![];
+        for (mask, path) in changes {
+            if mask.contains(EventMask::DELETE) {
+                if let Some(&id) = self.paths.get(&path) {
+                    events.extend(self.remove_device(id));
+                }
+            } else if !self.paths.contains_key(&path) {
+                // The node may be not accessible until udev sets its permissions (ATTRIB).
+                match Device::open(&path) {
+                    Ok(device) => {
+                        if let Err(err) = self.add_device(path, device) {
+                            error!("failed to add device: {err}");
+                        }
+                    }
+                    Err(err) => debug!("failed to open device {path:?}: {err}"),
+                }
+            }
         }
+        events
+    }
 
-        if device_map.is_empty() {
-            return Err(KeyboardError::KeyboardNotFound);
+    /// Grab the devices and start forwarding their key events.
+    fn grab(&mut self) -> Result<(), KeyboardError> {
+        if self.forwarder.is_some() {
+            return Ok(());
         }
+        // The virtual device is created first so that no key is lost after grabbing.
+        let forwarder = Forwarder::new()?;
+        for (&id, stream) in &mut self.devices {
+            if self.pointers.contains(&id) {
+                continue;
+            }
+            if !forwarder.covers(stream.device()) {
+                warn!(
+                    "device {:?} is not grabbed, it has keys that can't be forwarded",
+                    stream.device().name()
+                );
+                continue;
+            }
+            stream
+                .device_mut()
+                .grab()
+                .map_err(|err| KeyboardError::Io("grab device".to_string(), err))?;
+            self.grabbed.insert(id);
+        }
+        self.forwarder = Some(forwarder);
+        Ok(())
+    }
 
-        Ok(Self {
-            poll,
-            events: Events::with_capacity(32),
-            devices: device_map,
+    /// Forward the key events of the last read except the presses of the given keys
+    /// and their repeats and releases.
+    fn forward(&mut self, swallowed: &[Key]) -> Result<(), KeyboardError> {
+        match &mut self.forwarder {
+            Some(forwarder) => forwarder.forward(swallowed),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the LED is on on any of the devices.
+    fn led_state(&self, led: Led) -> bool {
+        self.devices.values().any(|stream| {
+            stream
+                .device()
+                .get_led_state()
+                .is_ok_and(|leds| leds.contains(evdev::LedType::from(led)))
         })
     }
 
-    /// Request keyboard events.
-    fn read(&mut self) -> Result<Vec<KeyEvent>, KeyboardError> {
-        let mut key_events = vec![];
+    /// Request keyboard events up to the end of a device report,
+    /// no events are returned if the deadline comes first.
+    /// The read is cancel safe: the events of an unfinished report are kept for the next read.
+    async fn read(
+        &mut self,
+        deadline: Option<SystemTime>,
+    ) -> Result<Vec<DeviceEvent>, KeyboardError> {
         loop {
-            if let Err(err) = self.poll.poll(&mut self.events, None) {
-                return Err(KeyboardError::Io("poll events".to_string(), err));
+            if let Some(report) = self.reports.pop_front() {
+                return Ok(report);
+            }
+            let timeout = deadline.map(|deadline| {
+                deadline
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default()
+            });
+            if timeout.is_some_and(|timeout| timeout.is_zero()) {
+                return Ok(vec![]);
             }
 
-            for event in &self.events {
-                if let Some(device) = self.devices.get_mut(&event.token()) {
-                    let device_events = device
-                        .fetch_events()
-                        .map_err(|err| KeyboardError::Io("fetch device events".to_string(), err))?;
-                    for device_event in device_events {
-                        match device_event.try_into() {
-                            Ok(key_event) => key_events.push(key_event),
-                            Err(err) => debug!("not implementerd input event: {}", err),
-                        };
+            tokio::select! {
+                () = sleep_or_pending(timeout) => {}
+                changes = read_changes(&mut self.watcher) => {
+                    let changes = changes
+                        .map_err(|err| KeyboardError::Io("read inotify events".to_string(), err))?;
+                    let events = self.apply_hotplug(changes);
+                    self.push_report(events)?;
+                }
+                (id, device_event) = next_event(&mut self.devices) => match device_event {
+                    Ok(device_event) => {
+                        if self.push_event(id, device_event) {
+                            let events = mem::take(&mut self.partial);
+                            self.push_report(events)?;
+                        }
                     }
-                } else {
-                    error!("an event was detected that does not belong to a registered device");
+                    Err(err) => {
+                        // Usually ENODEV after the device is unplugged.
+                        warn!("failed to fetch device events: {err}");
+                        let events = self.remove_device(id);
+                        self.push_report(events)?;
+                    }
+                },
+            }
+        }
+    }
+
+    fn push_report(&mut self, events: Vec<DeviceEvent>) -> Result<(), KeyboardError> {
+        if events.is_empty() {
+            // E.g. key repeats that the listener does not handle.
+            return self.forward(&[]);
+        }
+        self.reports.push_back(events);
+        Ok(())
+    }
+
+    /// Convert the device event into the events of the current report,
+    /// returns whether it ends the report.
+    fn push_event(&mut self, id: usize, device_event: InputEvent) -> bool {
+        if let Some(forwarder) = self
+            .forwarder
+            .as_mut()
+            .filter(|_| self.grabbed.contains(&id))
+        {
+            forwarder.push(device_event);
+        }
+        let event = match device_event.kind() {
+            evdev::InputEventKind::Synchronization(evdev::Synchronization::SYN_REPORT) => {
+                return true;
+            }
+            evdev::InputEventKind::Synchronization(_) => return false,
+            evdev::InputEventKind::Led(_) => device_event.try_into().map(DeviceEvent::Led),
+            evdev::InputEventKind::RelAxis(axis) => {
+                if let Some(key) = Key::from_wheel(axis, device_event.value()) {
+                    let event = KeyEvent {
+                        key,
+                        position: KeyPosition::Press,
+                    };
+                    self.partial
+                        .push(DeviceEvent::Key(event, device_event.timestamp()));
                 }
+                return false;
             }
-            if !key_events.is_empty() {
-                return Ok(key_events);
+            _ => device_event
+                .try_into()
+                .map(|event| DeviceEvent::Key(event, device_event.timestamp())),
+        };
+        match event {
+            Ok(event) => self.partial.push(event),
+            Err(err) => debug!("not implementerd input event: {}", err),
+        };
+        false
+    }
+}
+
+/// Sleep for the timeout, forever without it.
+async fn sleep_or_pending(timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => sleep(timeout).await,
+        None => future::pending().await,
+    }
+}
+
+/// Changes of the input directory: the event masks and the paths of the event nodes.
+async fn read_changes(watcher: &mut AsyncFd<Inotify>) -> io::Result<Vec<(EventMask, PathBuf)>> {
+    let mut buffer = [0; 1024];
+    loop {
+        let mut guard = watcher.readable_mut().await?;
+        let read = guard.try_io(|watcher| {
+            let events = watcher.get_mut().read_events(&mut buffer)?;
+            Ok(events
+                .filter_map(|event| {
+                    let name = event.name?;
+                    name.to_string_lossy()
+                        .starts_with("event")
+                        .then(|| (event.mask, Path::new(INPUT_DIR).join(name)))
+                })
+                .collect())
+        });
+        if let Ok(changes) = read {
+            return changes;
+        }
+    }
+}
+
+/// Next event of any of the devices, never ready without devices.
+async fn next_event(devices: &mut HashMap<usize, EventStream>) -> (usize, io::Result<InputEvent>) {
+    future::poll_fn(|cx| {
+        for (&id, stream) in devices.iter_mut() {
+            if let Poll::Ready(result) = stream.poll_event(cx) {
+                return Poll::Ready((id, result));
             }
         }
+        Poll::Pending
+    })
+    .await
+}
+
+/// Forwarding of the key events of grabbed devices through a virtual device.
+struct Forwarder {
+    device: VirtualDevice,
+    keys: AttributeSet<evdev::Key>,
+    pending: Vec<InputEvent>,
+    /// Keys whose press was not forwarded, so their repeats and release are not forwarded too.
+    swallowed: HashSet<Key>,
+}
+
+impl Forwarder {
+    /// The forwarder has all the keyboard keys, so keyboards plugged after the grab
+    /// are forwarded too.
+    fn new() -> Result<Self, KeyboardError> {
+        let keys = keyboard_keys();
+        let device = VirtualDeviceBuilder::new()
+            .map_err(|err| KeyboardError::Io("create virtual device".to_string(), err))?
+            .name(FORWARD_DEVICE_NAME)
+            .input_id(evdev::InputId::new(evdev::BusType::BUS_USB, 1, 1, 2))
+            .with_keys(&keys)
+            .map_err(|err| KeyboardError::Io("set up keys into virtual device".to_string(), err))?
+            .build()
+            .map_err(|err| KeyboardError::Io("build virtual device".to_string(), err))?;
+        Ok(Self {
+            device,
+            keys,
+            pending: vec![],
+            swallowed: HashSet::new(),
+        })
+    }
+
+    /// Whether all the keys of the device can be forwarded, a device is never grabbed otherwise
+    /// since its other keys would be lost.
+    fn covers(&self, device: &Device) -> bool {
+        device
+            .supported_keys()
+            .is_none_or(|keys| keys.iter().all(|key| self.keys.contains(key)))
+    }
+
+    fn push(&mut self, event: InputEvent) {
+        if event.event_type() == evdev::EventType::KEY {
+            self.pending.push(event);
+        }
+    }
+
+    fn forward(&mut self, swallowed: &[Key]) -> Result<(), KeyboardError> {
+        let mut events = vec![];
+        for event in self.pending.drain(..) {
+            let key = match event.kind() {
+                evdev::InputEventKind::Key(key) => Key::try_from(key).ok(),
+                _ => None,
+            };
+            if let Some(key) = key {
+                match event.value() {
+                    1 if swallowed.contains(&key) => {
+                        self.swallowed.insert(key);
+                        continue;
+                    }
+                    0 if self.swallowed.remove(&key) => continue,
+                    2 if self.swallowed.contains(&key) => continue,
+                    _ => {}
+                }
+            }
+            events.push(event);
+        }
+        if events.is_empty() {
+            return Ok(());
+        }
+        self.device
+            .emit(&events)
+            .map_err(|err| KeyboardError::Io("failed to forward events".to_string(), err))
     }
 }
 
 /// Keyboard emulator allows you to emulate keystrokes on a virtual keyboard.
 pub struct KeyboardEmulator {
     kw: KeyboardWriter,
+    key_delay: Duration,
 }
 
 impl KeyboardEmulator {
     pub fn new() -> Result<Self, KeyboardError> {
         let kw = KeyboardWriter::new()?;
-        Ok(Self { kw })
+        Ok(Self {
+            kw,
+            key_delay: Duration::ZERO,
+        })
+    }
+
+    /// Set the delay after each typed key, some applications skip keys typed too fast.
+    pub fn set_key_delay(&mut self, delay: Duration) {
+        self.key_delay = delay;
     }
 
     /// Simulate keypress ctrl + v.
     pub fn ctrl_v(&mut self) -> Result<(), KeyboardError> {
         use Key::*;
-        self.kw.press_keys(&[LCtrl, V])?;
-        self.kw.release_keys(&[LCtrl, V])
+        self.press_combination(&[LCtrl, V])
+    }
+
+    /// Simulate pressing the keys together and releasing them.
+    pub fn press_combination(&mut self, keys: &[Key]) -> Result<(), KeyboardError> {
+        self.kw.press_keys(keys)?;
+        let mut keys = keys.to_vec();
+        keys.reverse();
+        self.kw.release_keys(&keys)
+    }
+
+    /// Simulate typing the keys one by one, holding Shift for the keys marked with `true`.
+    pub async fn type_keys(&mut self, keys: &[(Key, bool)]) -> Result<(), KeyboardError> {
+        for &(key, shift) in keys {
+            self.type_key(key, shift).await?;
+        }
+        Ok(())
+    }
+
+    /// Simulate typing the text in the active layout.
+    /// Characters without a key in the layout are typed as their hex codes after Ctrl+Shift+U,
+    /// which works in GTK and Qt applications. Nothing is typed if the layout has no keys
+    /// for some of the characters or their hex digits.
+    pub async fn type_text(
+        &mut self,
+        text: &str,
+        layout: &KeyboardLayout,
+    ) -> Result<(), KeyboardError> {
+        use Key::*;
+        for keystroke in layout.keystrokes(text)? {
+            match keystroke {
+                Keystroke::Key(key, shift) => self.type_key(key, shift).await?,
+                Keystroke::Unicode(digits) => {
+                    self.press_combination(&[LCtrl, LShift, U])?;
+                    for digit in digits {
+                        self.type_key(digit, false).await?;
+                    }
+                    self.type_key(Space, false).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Simulate pressing backspace the given number of times.
+    pub async fn backspace(&mut self, count: usize) -> Result<(), KeyboardError> {
+        for _ in 0..count {
+            self.type_key(Key::BackSpace, false).await?;
+        }
+        Ok(())
+    }
+
+    async fn type_key(&mut self, key: Key, shift: bool) -> Result<(), KeyboardError> {
+        if shift {
+            self.press_combination(&[Key::LShift, key])?;
+        } else {
+            self.press_combination(&[key])?;
+        }
+        if !self.key_delay.is_zero() {
+            sleep(self.key_delay).await;
+        }
+        Ok(())
     }
 }
 
+/// Keys of the virtual keyboards, all the keys but the pointer buttons.
+fn keyboard_keys() -> AttributeSet<evdev::Key> {
+    AttributeSet::from_iter(
+        Key::iter()
+            .filter(|key| !key.is_pointer())
+            .filter_map(|key| evdev::Key::try_from(key).ok()),
+    )
+}
+
 /// Virtual keyboard.
 struct KeyboardWriter {
     device: VirtualDevice,
@@ -162,9 +789,9 @@ impl KeyboardWriter {
     fn new() -> Result<Self, KeyboardError> {
         let device = VirtualDeviceBuilder::new()
             .map_err(|err| KeyboardError::Io("create virtual device".to_string(), err))?
-            .name("dhe_keyboard")
+            .name(EMULATOR_DEVICE_NAME)
             .input_id(evdev::InputId::new(evdev::BusType::BUS_USB, 1, 1, 1))
-            .with_keys(&AttributeSet::from_iter(Key::iter().map(evdev::Key::from)))
+            .with_keys(&keyboard_keys())
             .map_err(|err| KeyboardError::Io("set up keys into virtual device".to_string(), err))?
             .build()
             .map_err(|err| KeyboardError::Io("build virtual device".to_string(), err))?;
@@ -174,7 +801,10 @@ impl KeyboardWriter {
 
     /// Simulate given events.
     fn write_events(&mut self, events: &[KeyEvent]) -> Result<(), KeyboardError> {
-        let raw_events: Vec<InputEvent> = events.iter().map(|&event| event.into()).collect();
+        let raw_events = events
+            .iter()
+            .map(|&event| event.try_into())
+            .collect::<Result<Vec<InputEvent>, _>>()?;
         self.device
             .emit(&raw_events)
             .map_err(|err| KeyboardError::Io("failed to emit event into device".to_string(), err))
@@ -204,3 +834,100 @@ impl KeyboardWriter {
         self.write_events(&events)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use tokio::time::timeout;
+
+    use super::*;
+
+    /// Listener whose reports are pushed by the test instead of read from devices.
+    fn listener() -> KeyboardListener {
+        let kr = KeyboardReader {
+            watcher: AsyncFd::new(Inotify::init().unwrap()).unwrap(),
+            filter: DeviceFilter::default(),
+            devices: HashMap::new(),
+            pointers: HashSet::new(),
+            grabbed: HashSet::new(),
+            read_pointers: false,
+            paths: HashMap::new(),
+            next_id: 0,
+            forwarder: None,
+            partial: vec![],
+            reports: VecDeque::new(),
+        };
+        KeyboardListener {
+            kr,
+            state: KeyboardState::default(),
+            actions: SequenceMatcher::new(DEFAULT_SEQUENCE_TIMEOUT),
+            layout_tracker: None,
+            last_events: vec![],
+            wheel_pressed: vec![],
+        }
+    }
+
+    fn report(listener: &mut KeyboardListener, key: Key, position: KeyPosition) {
+        let event = KeyEvent { key, position };
+        listener
+            .kr
+            .reports
+            .push_back(vec![DeviceEvent::Key(event, SystemTime::now())]);
+    }
+
+    #[tokio::test]
+    async fn actions_stream() {
+        use Key::*;
+        use KeyPosition::*;
+
+        let mut listener = listener();
+        listener.register_action("translate", &[LCtrl, T]);
+        listener.register_action("fix", &[LCtrl, F]);
+        for (key, position) in [
+            (LCtrl, Press),
+            (T, Press),
+            (T, Release),
+            (F, Press),
+            (F, Release),
+            (LCtrl, Release),
+        ] {
+            report(&mut listener, key, position);
+        }
+
+        let actions: Vec<_> = listener
+            .actions()
+            .take(2)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(vec!["translate", "fix"], actions);
+    }
+
+    #[tokio::test]
+    async fn cancelled_read() {
+        use Key::*;
+        use KeyPosition::*;
+
+        let mut listener = listener();
+        listener.register_action("translate", &[LCtrl, T]);
+        report(&mut listener, LCtrl, Press);
+        // The report with T is not finished by SYN_REPORT yet.
+        let event = KeyEvent {
+            key: T,
+            position: Press,
+        };
+        listener
+            .kr
+            .partial
+            .push(DeviceEvent::Key(event, SystemTime::now()));
+
+        assert_eq!(None, listener.get_action().await.unwrap());
+        let read = timeout(Duration::from_millis(10), listener.get_action()).await;
+        assert!(read.is_err());
+
+        let events = mem::take(&mut listener.kr.partial);
+        listener.kr.push_report(events).unwrap();
+        let action = listener.get_action().await.unwrap();
+        assert_eq!(Some(&"translate".to_string()), action);
+    }
+}
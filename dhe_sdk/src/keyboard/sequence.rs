@@ -0,0 +1,709 @@
+use std::{
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use super::{BindingKey, Key, KeyEvent, KeyPosition, KeyboardError, KeyboardState};
+
+/// Default maximum time between the steps of a sequence.
+pub const DEFAULT_SEQUENCE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Key combinations pressed one after another, like "LCtrl+Space, T, N".
+/// A usual shortcut is a sequence of one step.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySequence {
+    steps: Vec<Vec<BindingKey>>,
+}
+
+impl KeySequence {
+    pub fn new(steps: Vec<Vec<BindingKey>>) -> Self {
+        Self { steps }
+    }
+
+    pub fn steps(&self) -> &[Vec<BindingKey>] {
+        &self.steps
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.iter().all(Vec::is_empty)
+    }
+
+    /// Whether any step has a mouse button or a wheel direction.
+    pub fn has_pointer_keys(&self) -> bool {
+        self.steps
+            .iter()
+            .flatten()
+            .any(|key| matches!(key, BindingKey::Key(key) if key.is_pointer()))
+    }
+}
+
+impl From<Vec<Key>> for KeySequence {
+    fn from(keys: Vec<Key>) -> Self {
+        Self {
+            steps: vec![keys.into_iter().map(BindingKey::from).collect()],
+        }
+    }
+}
+
+impl From<Vec<BindingKey>> for KeySequence {
+    fn from(keys: Vec<BindingKey>) -> Self {
+        Self { steps: vec![keys] }
+    }
+}
+
+/// Steps are separated by commas, keys of a step are joined with `+`.
+impl FromStr for KeySequence {
+    type Err = KeyboardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err_gen = |err: String| KeyboardError::WrongKeySequence(s.to_string(), err);
+        let steps = s
+            .split(',')
+            .map(|step| {
+                step.split('+')
+                    .map(|key| match key.trim() {
+                        "" => Err(err_gen("empty key".to_string())),
+                        key => BindingKey::from_str(key)
+                            .map_err(|err| err_gen(format!("{key}: {err}"))),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { steps })
+    }
+}
+
+impl Display for KeySequence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let steps: Vec<String> = self
+            .steps
+            .iter()
+            .map(|step| {
+                step.iter()
+                    .map(BindingKey::to_string)
+                    .collect::<Vec<_>>()
+                    .join("+")
+            })
+            .collect();
+        write!(f, "{}", steps.join(", "))
+    }
+}
+
+/// Moment when an action bound to a sequence fires, the conditions apply to its last step.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Trigger {
+    /// When the keys become pressed.
+    Press,
+    /// When the pressed keys start to be released, if no other key was pressed.
+    Release,
+    /// When the keys are held for the duration.
+    LongPress(Duration),
+    /// When the keys are pressed the second time within the duration after the first press.
+    DoubleTap(Duration),
+}
+
+impl Trigger {
+    pub const DEFAULT_LONG_PRESS: Duration = Duration::from_millis(500);
+    pub const DEFAULT_DOUBLE_TAP: Duration = Duration::from_millis(300);
+}
+
+/// State machine that matches pressed key combinations against the registered sequences.
+///
+/// A step is made when a key press makes the pressed keys equal to a combination.
+/// While a prefix of a sequence is pending, pressing only modifiers keeps it,
+/// pressing Escape, another combination or waiting longer than the timeout cancels it.
+/// After the last step the action waits for the trigger condition of its binding.
+pub struct SequenceMatcher<A> {
+    bindings: Vec<Binding<A>>,
+    pending: Vec<KeyboardState>,
+    last_step: Option<SystemTime>,
+    armed: Option<Armed>,
+    timeout: Duration,
+    consumed: bool,
+    fired: Option<usize>,
+}
+
+struct Binding<A> {
+    steps: Vec<Combination>,
+    trigger: Trigger,
+    /// Whether the keys of the binding should not reach other applications.
+    grab: bool,
+    action: A,
+}
+
+/// Keys of a step: the exact keys and the modifiers pressed on either side, no other keys.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Combination {
+    exact: KeyboardState,
+    modifiers: Vec<KeyboardState>,
+    /// All the keys that may be pressed.
+    allowed: KeyboardState,
+}
+
+impl Combination {
+    fn new(keys: &[BindingKey]) -> Self {
+        let mut combination = Combination::default();
+        for key in keys {
+            match key {
+                BindingKey::Key(key) => {
+                    combination.exact.apply_keys(&[*key]);
+                    combination.allowed.apply_keys(&[*key]);
+                }
+                BindingKey::Modifier(modifier) => {
+                    let mut sides = KeyboardState::default();
+                    sides.apply_keys(&modifier.keys());
+                    combination.allowed.apply_keys(&modifier.keys());
+                    combination.modifiers.push(sides);
+                }
+            }
+        }
+        combination
+    }
+
+    /// Whether exactly the keys of the combination are pressed.
+    fn matches(&self, state: &KeyboardState) -> bool {
+        self.allows(state)
+            && state.contains(&self.exact)
+            && self.modifiers.iter().all(|sides| state.intersects(sides))
+    }
+
+    /// Whether no other keys than the keys of the combination are pressed.
+    fn allows(&self, state: &KeyboardState) -> bool {
+        self.allowed.contains(state)
+    }
+}
+
+/// Binding with all the steps made, waiting for its trigger condition.
+struct Armed {
+    index: usize,
+    /// Time of the last step.
+    time: SystemTime,
+}
+
+enum Match {
+    Full(usize),
+    /// Steps of a sequence are made, `grab` is set if any of the sequences is grabbed.
+    Prefix {
+        grab: bool,
+    },
+    None,
+}
+
+enum ArmedState {
+    Fire(usize),
+    Wait(Armed),
+    Drop,
+}
+
+impl<A> SequenceMatcher<A> {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            bindings: vec![],
+            pending: vec![],
+            last_step: None,
+            armed: None,
+            timeout,
+            consumed: false,
+            fired: None,
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Bind the action to the sequence, replacing the action bound to it before.
+    /// The keys of a grabbed sequence are reported as consumed, see `consumed`.
+    pub fn register(&mut self, sequence: &KeySequence, trigger: Trigger, grab: bool, action: A) {
+        let steps: Vec<Combination> = sequence
+            .steps()
+            .iter()
+            .map(|keys| Combination::new(keys))
+            .collect();
+        self.bindings.retain(|binding| binding.steps != steps);
+        self.bindings.push(Binding {
+            steps,
+            trigger,
+            grab,
+            action,
+        });
+    }
+
+    /// Remove all the bindings and forget the pending steps.
+    pub fn clear(&mut self) {
+        self.bindings.clear();
+        self.cancel();
+        self.consumed = false;
+        self.fired = None;
+    }
+
+    /// Whether the first steps of a sequence are made.
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Forget the pending steps.
+    pub fn cancel(&mut self) {
+        self.pending.clear();
+        self.last_step = None;
+        self.armed = None;
+    }
+
+    /// Time when a long press fires if nothing happens before,
+    /// the matcher should be applied with no events at that time.
+    pub fn deadline(&self) -> Option<SystemTime> {
+        let armed = self.armed.as_ref()?;
+        match self.bindings[armed.index].trigger {
+            Trigger::LongPress(duration) => Some(armed.time + duration),
+            _ => None,
+        }
+    }
+
+    /// Whether the key presses of the last applied events made a step of a grabbed sequence,
+    /// so they should not be passed to other applications.
+    pub fn consumed(&self) -> bool {
+        self.consumed
+    }
+
+    /// Match the state after the events that happened at the given time.
+    pub fn apply(
+        &mut self,
+        events: &[KeyEvent],
+        state: &KeyboardState,
+        time: SystemTime,
+    ) -> Option<&A> {
+        let pressed = events
+            .iter()
+            .any(|event| event.position == KeyPosition::Press);
+        self.consumed = false;
+        self.fired = self.step(pressed, state, time);
+        self.fired()
+    }
+
+    /// Action fired by the last applied events.
+    pub fn fired(&self) -> Option<&A> {
+        self.fired.map(|index| &self.bindings[index].action)
+    }
+
+    /// Index of the binding whose action fires.
+    fn step(&mut self, pressed: bool, state: &KeyboardState, time: SystemTime) -> Option<usize> {
+        if let Some(armed) = self.armed.take() {
+            match self.update_armed(armed, pressed, state, time) {
+                ArmedState::Fire(index) => {
+                    self.consumed = pressed && self.bindings[index].grab;
+                    return Some(index);
+                }
+                ArmedState::Wait(armed) => {
+                    self.armed = Some(armed);
+                    return None;
+                }
+                ArmedState::Drop => {}
+            }
+        }
+
+        if !pressed {
+            return None;
+        }
+
+        if let Some(last_step) = self.last_step {
+            if time.duration_since(last_step).unwrap_or_default() > self.timeout {
+                self.cancel();
+            }
+        }
+
+        if self.is_pending() {
+            if state.is_pressed(Key::Escape) {
+                self.cancel();
+                return None;
+            }
+
+            let mut steps = self.pending.clone();
+            steps.push(state.clone());
+            match self.find(&steps) {
+                Match::Full(index) => {
+                    self.cancel();
+                    return self.trigger(index, time);
+                }
+                Match::Prefix { grab } => {
+                    self.pending = steps;
+                    self.last_step = Some(time);
+                    self.consumed = grab;
+                    return None;
+                }
+                Match::None if state.only_modifiers() => return None,
+                Match::None => self.cancel(),
+            }
+        }
+
+        let steps = vec![state.clone()];
+        match self.find(&steps) {
+            Match::Full(index) => self.trigger(index, time),
+            Match::Prefix { grab } => {
+                self.pending = steps;
+                self.last_step = Some(time);
+                self.consumed = grab;
+                None
+            }
+            Match::None => None,
+        }
+    }
+
+    /// Fire the action of the binding or wait for its trigger condition.
+    fn trigger(&mut self, index: usize, time: SystemTime) -> Option<usize> {
+        self.consumed = self.bindings[index].grab;
+        if self.bindings[index].trigger == Trigger::Press {
+            return Some(index);
+        }
+        self.armed = Some(Armed { index, time });
+        None
+    }
+
+    fn update_armed(
+        &self,
+        armed: Armed,
+        pressed: bool,
+        state: &KeyboardState,
+        time: SystemTime,
+    ) -> ArmedState {
+        let keys = self.bindings[armed.index].steps.last();
+        let held = keys.is_some_and(|keys| keys.matches(state));
+        let released = !pressed && keys.is_some_and(|keys| keys.allows(state));
+        let elapsed = time.duration_since(armed.time).unwrap_or_default();
+
+        match self.bindings[armed.index].trigger {
+            Trigger::Press => ArmedState::Drop,
+            Trigger::Release if held => ArmedState::Wait(armed),
+            Trigger::Release if released => ArmedState::Fire(armed.index),
+            Trigger::Release => ArmedState::Drop,
+            Trigger::LongPress(duration) if held && elapsed >= duration => {
+                ArmedState::Fire(armed.index)
+            }
+            Trigger::LongPress(_) if held => ArmedState::Wait(armed),
+            Trigger::LongPress(_) => ArmedState::Drop,
+            Trigger::DoubleTap(duration) if elapsed > duration => ArmedState::Drop,
+            Trigger::DoubleTap(_) if pressed && held => ArmedState::Fire(armed.index),
+            // Releasing the keys or pressing them again one by one.
+            Trigger::DoubleTap(_) if released || !pressed => ArmedState::Wait(armed),
+            Trigger::DoubleTap(_) if keys.is_some_and(|keys| keys.allows(state)) => {
+                ArmedState::Wait(armed)
+            }
+            Trigger::DoubleTap(_) => ArmedState::Drop,
+        }
+    }
+
+    /// A sequence matching the steps or starting with them.
+    fn find(&self, steps: &[KeyboardState]) -> Match {
+        let mut found = Match::None;
+        for (index, binding) in self.bindings.iter().enumerate() {
+            if binding.steps.len() < steps.len()
+                || !binding
+                    .steps
+                    .iter()
+                    .zip(steps)
+                    .all(|(combination, state)| combination.matches(state))
+            {
+                continue;
+            }
+            if binding.steps.len() == steps.len() {
+                return Match::Full(index);
+            }
+            found = match found {
+                Match::Prefix { grab } => Match::Prefix {
+                    grab: grab || binding.grab,
+                },
+                _ => Match::Prefix { grab: binding.grab },
+            };
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Keyboard {
+        matcher: SequenceMatcher<&'static str>,
+        state: KeyboardState,
+        time: SystemTime,
+    }
+
+    impl Keyboard {
+        fn new(sequences: &[(&str, &'static str)]) -> Self {
+            let bindings: Vec<_> = sequences
+                .iter()
+                .map(|&(sequence, action)| (sequence, Trigger::Press, action))
+                .collect();
+            Self::with_triggers(&bindings)
+        }
+
+        fn with_triggers(bindings: &[(&str, Trigger, &'static str)]) -> Self {
+            let mut matcher = SequenceMatcher::new(DEFAULT_SEQUENCE_TIMEOUT);
+            for &(sequence, trigger, action) in bindings {
+                matcher.register(&sequence.parse().unwrap(), trigger, false, action);
+            }
+            Self {
+                matcher,
+                state: KeyboardState::default(),
+                time: SystemTime::UNIX_EPOCH,
+            }
+        }
+
+        fn event(&mut self, key: Key, position: KeyPosition) -> Option<&'static str> {
+            self.time += Duration::from_millis(100);
+            let events = [KeyEvent { key, position }];
+            self.state.apply_events(&events);
+            self.matcher.apply(&events, &self.state, self.time).copied()
+        }
+
+        /// Nothing happens for the duration.
+        fn wait(&mut self, duration: Duration) -> Option<&'static str> {
+            self.time += duration;
+            self.matcher.apply(&[], &self.state, self.time).copied()
+        }
+
+        /// Press the keys one by one and release them, returning the fired actions.
+        fn tap(&mut self, keys: &[Key]) -> Vec<&'static str> {
+            let mut actions = vec![];
+            for &key in keys {
+                actions.extend(self.event(key, KeyPosition::Press));
+            }
+            for &key in keys.iter().rev() {
+                actions.extend(self.event(key, KeyPosition::Release));
+            }
+            actions
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let sequence: KeySequence = "LCtrl + Space, T,N".parse().unwrap();
+        assert_eq!(
+            KeySequence::new(vec![
+                vec![Key::LCtrl.into(), Key::Space.into()],
+                vec![Key::T.into()],
+                vec![Key::N.into()]
+            ]),
+            sequence
+        );
+        assert_eq!("LCtrl+Space, T, N", sequence.to_string());
+        assert!("LCtrl+, T".parse::<KeySequence>().is_err());
+        assert!("LCtrl+Foo".parse::<KeySequence>().is_err());
+    }
+
+    #[test]
+    fn sequence() {
+        use Key::*;
+        let mut keyboard = Keyboard::new(&[("LCtrl+Space, T, N", "notify"), ("LAlt+T", "paste")]);
+
+        assert!(keyboard.tap(&[LCtrl, Space]).is_empty());
+        assert!(keyboard.matcher.is_pending());
+        assert!(keyboard.tap(&[T]).is_empty());
+        assert_eq!(vec!["notify"], keyboard.tap(&[N]));
+        assert!(!keyboard.matcher.is_pending());
+
+        assert_eq!(vec!["paste"], keyboard.tap(&[LAlt, T]));
+    }
+
+    #[test]
+    fn modifiers_keep_pending_steps() {
+        use Key::*;
+        let mut keyboard = Keyboard::new(&[("LCtrl+Space, LAlt+T", "notify")]);
+
+        keyboard.tap(&[LCtrl, Space]);
+        assert_eq!(vec!["notify"], keyboard.tap(&[LAlt, T]));
+    }
+
+    #[test]
+    fn cancel() {
+        use Key::*;
+        let mut keyboard = Keyboard::new(&[("LCtrl+Space, T, N", "notify"), ("LAlt+T", "paste")]);
+
+        keyboard.tap(&[LCtrl, Space]);
+        keyboard.tap(&[Escape]);
+        assert!(!keyboard.matcher.is_pending());
+        assert!(keyboard.tap(&[T, N]).is_empty());
+
+        // Another combination cancels the pending steps and is matched itself.
+        keyboard.tap(&[LCtrl, Space]);
+        assert_eq!(vec!["paste"], keyboard.tap(&[LAlt, T]));
+        assert!(keyboard.tap(&[N]).is_empty());
+    }
+
+    #[test]
+    fn clear() {
+        use Key::*;
+        let mut keyboard = Keyboard::new(&[("LCtrl+Space, T", "notify")]);
+
+        keyboard.tap(&[LCtrl, Space]);
+        keyboard.matcher.clear();
+        assert!(!keyboard.matcher.is_pending());
+        assert!(keyboard.tap(&[T]).is_empty());
+
+        keyboard
+            .matcher
+            .register(&"LCtrl+T".parse().unwrap(), Trigger::Press, false, "paste");
+        assert!(keyboard.tap(&[LCtrl, Space]).is_empty());
+        assert_eq!(vec!["paste"], keyboard.tap(&[LCtrl, T]));
+    }
+
+    #[test]
+    fn timeout() {
+        use Key::*;
+        let mut keyboard = Keyboard::new(&[("LCtrl+Space, T", "notify")]);
+
+        keyboard.tap(&[LCtrl, Space]);
+        keyboard.time += Duration::from_secs(2);
+        assert!(keyboard.tap(&[T]).is_empty());
+        assert!(!keyboard.matcher.is_pending());
+    }
+
+    #[test]
+    fn press_fires_once() {
+        use Key::*;
+        let mut keyboard = Keyboard::new(&[("LAlt+T", "paste")]);
+
+        assert_eq!(None, keyboard.event(LAlt, KeyPosition::Press));
+        assert_eq!(Some("paste"), keyboard.event(T, KeyPosition::Press));
+        // Pressing and releasing another key while holding the combination does not fire it again.
+        assert_eq!(None, keyboard.event(Q, KeyPosition::Press));
+        assert_eq!(None, keyboard.event(Q, KeyPosition::Release));
+    }
+
+    #[test]
+    fn release() {
+        use Key::*;
+        let mut keyboard = Keyboard::with_triggers(&[("LAlt+T", Trigger::Release, "paste")]);
+
+        assert_eq!(None, keyboard.event(LAlt, KeyPosition::Press));
+        assert_eq!(None, keyboard.event(T, KeyPosition::Press));
+        assert_eq!(Some("paste"), keyboard.event(T, KeyPosition::Release));
+        assert_eq!(None, keyboard.event(LAlt, KeyPosition::Release));
+
+        // Another key pressed in between cancels the action.
+        keyboard.event(LAlt, KeyPosition::Press);
+        keyboard.event(T, KeyPosition::Press);
+        keyboard.event(Q, KeyPosition::Press);
+        assert_eq!(None, keyboard.event(Q, KeyPosition::Release));
+        assert_eq!(None, keyboard.event(T, KeyPosition::Release));
+    }
+
+    #[test]
+    fn long_press() {
+        use Key::*;
+        let duration = Duration::from_millis(500);
+        let mut keyboard =
+            Keyboard::with_triggers(&[("LCtrl", Trigger::LongPress(duration), "gui")]);
+
+        assert_eq!(None, keyboard.event(LCtrl, KeyPosition::Press));
+        assert_eq!(Some(keyboard.time + duration), keyboard.matcher.deadline());
+        assert_eq!(None, keyboard.wait(Duration::from_millis(200)));
+        assert_eq!(Some("gui"), keyboard.wait(Duration::from_millis(300)));
+        assert_eq!(None, keyboard.wait(Duration::from_millis(500)));
+        keyboard.event(LCtrl, KeyPosition::Release);
+
+        // A short press or a shortcut with the key does not fire.
+        assert!(keyboard.tap(&[LCtrl]).is_empty());
+        keyboard.event(LCtrl, KeyPosition::Press);
+        keyboard.event(C, KeyPosition::Press);
+        assert_eq!(None, keyboard.matcher.deadline());
+        assert_eq!(None, keyboard.wait(duration));
+    }
+
+    #[test]
+    fn double_tap() {
+        use Key::*;
+        let duration = Duration::from_millis(300);
+        let mut keyboard =
+            Keyboard::with_triggers(&[("LShift", Trigger::DoubleTap(duration), "retype")]);
+
+        assert!(keyboard.tap(&[LShift]).is_empty());
+        assert_eq!(vec!["retype"], keyboard.tap(&[LShift]));
+        assert!(keyboard.tap(&[LShift]).is_empty());
+
+        // Typing a capital letter does not count as a tap.
+        keyboard.time += duration;
+        assert!(keyboard.tap(&[LShift, A]).is_empty());
+        assert!(keyboard.tap(&[LShift]).is_empty());
+        keyboard.time += duration;
+        assert!(keyboard.tap(&[LShift]).is_empty());
+    }
+
+    #[test]
+    fn logical_modifiers() {
+        use Key::*;
+        let mut keyboard = Keyboard::new(&[("Ctrl+Space, T", "notify"), ("Shift+LAlt+T", "paste")]);
+
+        keyboard.tap(&[RCtrl, Space]);
+        assert_eq!(vec!["notify"], keyboard.tap(&[T]));
+        keyboard.tap(&[LCtrl, Space]);
+        assert_eq!(vec!["notify"], keyboard.tap(&[T]));
+
+        assert_eq!(vec!["paste"], keyboard.tap(&[RShift, LAlt, T]));
+        assert_eq!(vec!["paste"], keyboard.tap(&[LShift, RShift, LAlt, T]));
+        // The exact side is required for the physical keys.
+        assert!(keyboard.tap(&[LShift, RAlt, T]).is_empty());
+        // Other keys are not allowed.
+        assert!(keyboard.tap(&[LShift, LCtrl, LAlt, T]).is_empty());
+    }
+
+    #[test]
+    fn media_keys() {
+        use Key::*;
+        let mut keyboard = Keyboard::new(&[("VolumeUp+Super", "louder"), ("F24", "f24")]);
+
+        assert_eq!(vec!["louder"], keyboard.tap(&[RWin, VolumeUp]));
+        assert_eq!(vec!["f24"], keyboard.tap(&[F24]));
+        assert!(keyboard.tap(&[VolumeDown, LWin]).is_empty());
+    }
+
+    #[test]
+    fn mouse_keys() {
+        use Key::*;
+        let mut keyboard =
+            Keyboard::new(&[("Super+MouseMiddle", "translate"), ("Ctrl+WheelUp", "zoom")]);
+
+        assert_eq!(vec!["translate"], keyboard.tap(&[LWin, MouseMiddle]));
+        assert!(keyboard.tap(&[MouseMiddle]).is_empty());
+
+        // Each wheel step fires while the modifier is held.
+        keyboard.event(RCtrl, KeyPosition::Press);
+        assert_eq!(Some("zoom"), keyboard.event(WheelUp, KeyPosition::Press));
+        keyboard.event(WheelUp, KeyPosition::Release);
+        assert_eq!(Some("zoom"), keyboard.event(WheelUp, KeyPosition::Press));
+        keyboard.event(WheelUp, KeyPosition::Release);
+        assert_eq!(None, keyboard.event(WheelDown, KeyPosition::Press));
+
+        let sequence: KeySequence = "Ctrl+WheelDown".parse().unwrap();
+        assert!(sequence.has_pointer_keys());
+        assert!(!"Ctrl+T".parse::<KeySequence>().unwrap().has_pointer_keys());
+    }
+
+    #[test]
+    fn consumed() {
+        use Key::*;
+        let mut keyboard = Keyboard::new(&[("LAlt+T", "paste")]);
+        keyboard.matcher.register(
+            &"LCtrl+Space, T".parse().unwrap(),
+            Trigger::Press,
+            true,
+            "notify",
+        );
+
+        keyboard.event(LCtrl, KeyPosition::Press);
+        assert!(!keyboard.matcher.consumed());
+        keyboard.event(Space, KeyPosition::Press);
+        assert!(keyboard.matcher.consumed());
+        keyboard.event(Space, KeyPosition::Release);
+        keyboard.event(LCtrl, KeyPosition::Release);
+        assert!(!keyboard.matcher.consumed());
+        assert_eq!(Some("notify"), keyboard.event(T, KeyPosition::Press));
+        assert!(keyboard.matcher.consumed());
+        keyboard.event(T, KeyPosition::Release);
+
+        keyboard.event(LAlt, KeyPosition::Press);
+        assert_eq!(Some("paste"), keyboard.event(T, KeyPosition::Press));
+        assert!(!keyboard.matcher.consumed());
+    }
+}
@@ -0,0 +1,157 @@
+use super::{Key, KeyEvent, KeyPosition, QWERTY};
+
+/// Physical keys of the last typed word, so that the word can be retyped in another layout.
+#[derive(Debug, Default, Clone)]
+pub struct WordBuffer {
+    /// Keys of the word and whether Shift was held.
+    word: Vec<(Key, bool)>,
+    /// Number of spaces typed after the word.
+    spaces: usize,
+    shift: bool,
+    modifiers: usize,
+}
+
+impl WordBuffer {
+    /// Keys of the last word with the Shift state.
+    pub fn word(&self) -> &[(Key, bool)] {
+        &self.word
+    }
+
+    /// Number of spaces typed after the last word.
+    pub fn trailing_spaces(&self) -> usize {
+        self.spaces
+    }
+
+    /// Number of characters to erase to remove the word with its trailing spaces.
+    pub fn len(&self) -> usize {
+        self.word.len() + self.spaces
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.word.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.word.clear();
+        self.spaces = 0;
+    }
+
+    pub fn apply_events(&mut self, events: &[KeyEvent]) {
+        events.iter().for_each(|&event| self.apply_event(event));
+    }
+
+    fn apply_event(&mut self, KeyEvent { key, position }: KeyEvent) {
+        use Key::*;
+
+        let pressed = position == KeyPosition::Press;
+        match key {
+            LShift | RShift => self.shift = pressed,
+            LCtrl | RCtrl | LAlt | RAlt | LWin | RWin => {
+                self.modifiers = if pressed {
+                    self.modifiers + 1
+                } else {
+                    self.modifiers.saturating_sub(1)
+                };
+            }
+            CapsLock => {}
+            _ if !pressed => {}
+            // Shortcuts do not type text.
+            _ if self.modifiers > 0 => self.clear(),
+            BackSpace => {
+                if self.spaces > 0 {
+                    self.spaces -= 1;
+                } else {
+                    self.word.pop();
+                }
+            }
+            Space if !self.word.is_empty() => self.spaces += 1,
+            key if is_character(key) => {
+                if self.spaces > 0 {
+                    self.clear();
+                }
+                self.word.push((key, self.shift));
+            }
+            _ => self.clear(),
+        }
+    }
+}
+
+/// Whether the key types a character of a word.
+fn is_character(key: Key) -> bool {
+    QWERTY
+        .char_of(key, false)
+        .is_some_and(|c| !c.is_whitespace())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::JCUKEN;
+
+    fn tap(buffer: &mut WordBuffer, keys: &[Key]) {
+        for &key in keys {
+            buffer.apply_events(&[
+                KeyEvent {
+                    key,
+                    position: KeyPosition::Press,
+                },
+                KeyEvent {
+                    key,
+                    position: KeyPosition::Release,
+                },
+            ]);
+        }
+    }
+
+    fn typed(buffer: &WordBuffer) -> String {
+        buffer
+            .word()
+            .iter()
+            .filter_map(|&(key, shift)| JCUKEN.char_of(key, shift))
+            .collect()
+    }
+
+    #[test]
+    fn last_word() {
+        use Key::*;
+        let mut buffer = WordBuffer::default();
+        tap(&mut buffer, &[H, E, L, L, O, Space, G, H, B, D, T, N]);
+        assert_eq!("привет", typed(&buffer));
+        assert_eq!(6, buffer.len());
+
+        tap(&mut buffer, &[Space, Space]);
+        assert_eq!("привет", typed(&buffer));
+        assert_eq!(8, buffer.len());
+
+        tap(&mut buffer, &[BackSpace, BackSpace, BackSpace]);
+        assert_eq!("приве", typed(&buffer));
+        assert_eq!(0, buffer.trailing_spaces());
+    }
+
+    #[test]
+    fn shift_and_shortcuts() {
+        use Key::*;
+        let mut buffer = WordBuffer::default();
+        buffer.apply_events(&[KeyEvent {
+            key: LShift,
+            position: KeyPosition::Press,
+        }]);
+        tap(&mut buffer, &[G]);
+        buffer.apply_events(&[KeyEvent {
+            key: LShift,
+            position: KeyPosition::Release,
+        }]);
+        tap(&mut buffer, &[H]);
+        assert_eq!("Пр", typed(&buffer));
+
+        buffer.apply_events(&[KeyEvent {
+            key: LCtrl,
+            position: KeyPosition::Press,
+        }]);
+        tap(&mut buffer, &[V]);
+        assert!(buffer.is_empty());
+
+        tap(&mut buffer, &[Escape]);
+        assert!(buffer.is_empty());
+    }
+}
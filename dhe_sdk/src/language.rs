@@ -1,19 +1,45 @@
-use std::fmt::Display;
+use std::{collections::HashSet, fmt::Display};
 
 use lingua::LanguageDetectorBuilder;
-use strum::{EnumIter, IntoEnumIterator};
+use serde::{Deserialize, Serialize};
+use strum::{EnumIter, EnumString, IntoEnumIterator};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum LanguageError {
     #[error("the specified language is not supported")]
     LanguageNotSupported,
+    #[error("the language is not recognized with enough confidence")]
+    LowConfidence,
+    #[error("threshold \"{0}\" is out of range")]
+    ThresholdOutOfRange(f64),
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter)]
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    EnumIter,
+    EnumString,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
 pub enum Language {
     En,
     Ru,
+    #[cfg(feature = "german")]
+    De,
+    #[cfg(feature = "ukrainian")]
+    Uk,
+    #[cfg(feature = "spanish")]
+    Es,
 }
 
 impl From<Language> for lingua::Language {
@@ -22,17 +48,34 @@ impl From<Language> for lingua::Language {
         match value {
             En => lingua::Language::English,
             Ru => lingua::Language::Russian,
+            #[cfg(feature = "german")]
+            De => lingua::Language::German,
+            #[cfg(feature = "ukrainian")]
+            Uk => lingua::Language::Ukrainian,
+            #[cfg(feature = "spanish")]
+            Es => lingua::Language::Spanish,
         }
     }
 }
 
-impl From<lingua::Language> for Language {
-    fn from(value: lingua::Language) -> Self {
+impl TryFrom<lingua::Language> for Language {
+    type Error = LanguageError;
+    fn try_from(value: lingua::Language) -> Result<Self, Self::Error> {
         use Language::*;
-        match value {
+        let language = match value {
             lingua::Language::English => En,
             lingua::Language::Russian => Ru,
-        }
+            #[cfg(feature = "german")]
+            lingua::Language::German => De,
+            #[cfg(feature = "ukrainian")]
+            lingua::Language::Ukrainian => Uk,
+            #[cfg(feature = "spanish")]
+            lingua::Language::Spanish => Es,
+            // Reachable when lingua is built with languages that are not enabled here.
+            #[allow(unreachable_patterns)]
+            _ => return Err(LanguageError::LanguageNotSupported),
+        };
+        Ok(language)
     }
 }
 
@@ -42,30 +85,294 @@ impl Display for Language {
         match self {
             En => write!(f, "en"),
             Ru => write!(f, "ru"),
+            #[cfg(feature = "german")]
+            De => write!(f, "de"),
+            #[cfg(feature = "ukrainian")]
+            Uk => write!(f, "uk"),
+            #[cfg(feature = "spanish")]
+            Es => write!(f, "es"),
         }
     }
 }
 
+/// Letter pairs common in English words, about the 250 most frequent ones.
+const EN_BIGRAMS: &str = "th he in er an re on at en nd ti es or te of ed is it al ar st to nt ng \
+se ha as ou io le ve co me de hi ri ro ic ne ea ra ce li ch ll be ma si om ur ca el ta la ns di \
+fo ho pe ec pr no ct us ac ot il tr ly nc et ut ss so rs un lo wa ge ie wh ee wi em ad ol rt po \
+we na ul ni ts mo ow pa im mi ai sh ir su id os iv ia am fi ci vi pl ig tu ev ld ry mp fe bl ab \
+gh ty op wo sa ay ex ke fr oo av ag if ap gr od bo sp rd do uc bu ei ov by rm ep tt oc fa ef cu \
+rn sc gi da yo cr cl du ga qu ue ff ba ey ls va um pp ua up lu go ht ru ug ds lt pi rc rr eg au \
+ck ew mu br bi pt ak pu ui rg ib tl ny ki rk ys ob mm fu ph og ms ye ud mb ip ub oi rl gu dr hr \
+cc tw ft wn nu af hu nn eo vo rv nf xp gn sm fl iz ok nl my gl aw ju oa sy sl ps jo lf ks";
+
+/// Letter pairs common in Russian words, "ё" is counted as "е".
+const RU_BIGRAMS: &str = "аб ав аг ад аж аз ай ак ал ам ан ап ар ас ат ах ац ач аш ая ае аю ба бе би бл бн бо бр бс бу бъ \
+бы бь бя ва вг вд ве вз ви вк вл вм вн во вп вр вс вт ву вх вш вы вь вя га гд ге ги гл гн го гр \
+гу да дв де дж ди дл дм дн до др ду ды дь дя еб ев ег ед еж ез ей ек ел ем ен ео еп ер ес ет ех \
+ец еч еш ещ ею ея жа жд же жи жк жн жо жу за зб зв зг зд зе зи зк зл зм зн зо зр зу зы зь зя иб \
+ив иг ид ие иж из ии ий ик ил им ин ио ип ир ис ит их иц ич иш ищ ию ия йн йс йт ка кв ке ки кл \
+кн ко кр кс кт ку ла лг ле лж ли лк лл лн ло лс лт лу лы ль лю ля ма ме ми мк мн мо мп му мы мь \
+мя на нг нд не нж нз ни нк нн но нс нт ну нц нч нщ ны нь ню ня об ов ог од ое ож оз ои ой ок ол \
+ом он оп ор ос от ох оц оч ош ощ ою оя па пе пи пл по пр пт пу пы пь пя ра рб рв рг рд ре рж рз \
+ри рк рл рм рн ро рп рр рс рт ру рх рц рч рш ры рь рю ря са сб св се си ск сл см сн со сп ср сс \
+ст су сх сч сы сь ся та тв те ти тк тл тн то тр тс тт ту ты ть тю тя уа уб ув уг уд уе уж уз уй \
+ук ул ум ун уп ур ус ут ух уч уш ущ ую уя фа фе фи фо фр фу ха хв хи хл хн хо хр ху ца це ци цо \
+цу цы ча че чи чк чн чт чу чь ша ше ши шк шл шн шо шт шу шь ща ще щи щн ъе ъя ыв ыг ыд ые ыж ый \
+ык ыл ым ын ып ыр ыс ыт ых ыш ье ьз ьк ьм ьн ьс ьт ьш ью ья эк эл эм эн эт юб юд юж юз юм ют юч \
+ющ яв яг яд яж яз яй ял ям ян яс ят ях яц яч ящ";
+
+/// Scorer of how much a text is like a real text in some language rather than random letters.
+pub trait TextScorer: Send + Sync {
+    /// Score from 0 to 1, `None` if the text has nothing to score.
+    fn score(&self, text: &str) -> Option<f64>;
+}
+
+/// Scorer by the share of the letter pairs of the text that are common in the language.
+/// It tells a word from the keys typed in a wrong layout, e.g. "ghbdtn" from "привет",
+/// unlike the language detector which is sure of both since each is in the script of its layout.
+#[derive(Debug, Clone)]
+pub struct BigramScorer {
+    bigrams: HashSet<(char, char)>,
+}
+
+impl BigramScorer {
+    /// Scorer by the lowercase letter pairs separated by whitespace, e.g. "th he in".
+    pub fn new(bigrams: &str) -> Self {
+        let bigrams = bigrams
+            .split_whitespace()
+            .filter_map(|bigram| {
+                let mut chars = bigram.chars();
+                Some((chars.next()?, chars.next()?))
+            })
+            .collect();
+        Self { bigrams }
+    }
+
+    /// Built-in scorer of the language, `None` if there is no table of its letter pairs.
+    pub fn for_language(language: Language) -> Option<Self> {
+        match language {
+            Language::En => Some(Self::new(EN_BIGRAMS)),
+            Language::Ru => Some(Self::new(RU_BIGRAMS)),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
+impl TextScorer for BigramScorer {
+    /// `None` if the text has no letter pairs.
+    fn score(&self, text: &str) -> Option<f64> {
+        let text = text.to_lowercase().replace('ё', "е");
+        let (mut total, mut common) = (0, 0);
+        // Non-letters inside a word, e.g. "," typed by the key of "б", make uncommon pairs.
+        for word in text.split_whitespace() {
+            let chars: Vec<char> = word
+                .trim_matches(|c: char| !c.is_alphabetic())
+                .chars()
+                .collect();
+            for pair in chars.windows(2) {
+                total += 1;
+                if self.bigrams.contains(&(pair[0], pair[1])) {
+                    common += 1;
+                }
+            }
+        }
+        (total > 0).then(|| common as f64 / total as f64)
+    }
+}
+
 pub struct LanguageDetector {
     detector: lingua::LanguageDetector,
+    min_confidence: f64,
+    min_relative_distance: f64,
 }
 
 impl LanguageDetector {
     pub fn new() -> Self {
+        Self::build(0.0, 0.0)
+    }
+
+    /// Detector that does not recognize a language if its confidence is below `min_confidence`
+    /// or if the distance to the next most likely language is below `min_relative_distance`.
+    pub fn with_thresholds(
+        min_confidence: f64,
+        min_relative_distance: f64,
+    ) -> Result<Self, LanguageError> {
+        if !(0.0..=1.0).contains(&min_confidence) {
+            return Err(LanguageError::ThresholdOutOfRange(min_confidence));
+        }
+        if !(0.0..=0.99).contains(&min_relative_distance) {
+            return Err(LanguageError::ThresholdOutOfRange(min_relative_distance));
+        }
+        Ok(Self::build(min_confidence, min_relative_distance))
+    }
+
+    fn build(min_confidence: f64, min_relative_distance: f64) -> Self {
         let languages: Vec<lingua::Language> = Language::iter().map(|lang| lang.into()).collect();
-        let detector = LanguageDetectorBuilder::from_languages(&languages).build();
-        Self { detector }
+        let detector = LanguageDetectorBuilder::from_languages(&languages)
+            .with_minimum_relative_distance(min_relative_distance)
+            .build();
+        Self {
+            detector,
+            min_confidence,
+            min_relative_distance,
+        }
     }
 
     pub fn recognize(&self, text: &str) -> Result<Language, LanguageError> {
-        self.detector
+        let language = self
+            .detector
             .detect_language_of(text)
-            .map(|detected_language| detected_language.into())
-            .ok_or(LanguageError::LanguageNotSupported)
+            .ok_or(LanguageError::LowConfidence)?;
+        let confidence = self.detector.compute_language_confidence(text, language);
+        if confidence < self.min_confidence {
+            return Err(LanguageError::LowConfidence);
+        }
+        language.try_into()
+    }
+
+    /// Confidence values of all supported languages for the text, from the most likely to the least.
+    pub fn confidences(&self, text: &str) -> Vec<(Language, f64)> {
+        self.detector
+            .compute_language_confidence_values(text)
+            .into_iter()
+            .filter_map(|(language, confidence)| {
+                Language::try_from(language)
+                    .ok()
+                    .map(|language| (language, confidence))
+            })
+            .collect()
+    }
+
+    /// Recognition that combines the detected confidences with a prior belief in the `prior` language,
+    /// e.g. the active keyboard layout. The prior language gets the `weight` share of the prior
+    /// probability and the other languages share the rest equally. The thresholds are applied
+    /// to the combined scores normalized to sum up to 1.
+    pub fn recognize_with_prior(
+        &self,
+        text: &str,
+        prior: Language,
+        weight: f64,
+    ) -> Result<Language, LanguageError> {
+        if !(0.0..=1.0).contains(&weight) {
+            return Err(LanguageError::ThresholdOutOfRange(weight));
+        }
+        let others_weight = (1.0 - weight) / (Language::iter().count() - 1).max(1) as f64;
+        let scores: Vec<(Language, f64)> = self
+            .confidences(text)
+            .into_iter()
+            .map(|(language, confidence)| {
+                let prior_weight = if language == prior {
+                    weight
+                } else {
+                    others_weight
+                };
+                (language, confidence * prior_weight)
+            })
+            .collect();
+        let total: f64 = scores.iter().map(|(_, score)| score).sum();
+        if total <= 0.0 {
+            return Err(LanguageError::LowConfidence);
+        }
+        let mut scores: Vec<(Language, f64)> = scores
+            .into_iter()
+            .map(|(language, score)| (language, score / total))
+            .collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let (language, score) = scores[0];
+        let next_score = scores.get(1).map_or(0.0, |&(_, score)| score);
+        if score < self.min_confidence || score - next_score < self.min_relative_distance {
+            return Err(LanguageError::LowConfidence);
+        }
+        Ok(language)
     }
 }
+
 impl Default for LanguageDetector {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confidences() {
+        let detector = LanguageDetector::new();
+        let confidences = detector.confidences("the quick brown fox");
+        assert_eq!(Language::En, confidences[0].0);
+        assert!(confidences.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+
+    #[test]
+    fn recognize() {
+        let detector = LanguageDetector::with_thresholds(0.9, 0.5).unwrap();
+        assert_eq!(
+            Language::Ru,
+            detector.recognize("привет, как дела").unwrap()
+        );
+        assert!(matches!(
+            detector.recognize("123"),
+            Err(LanguageError::LowConfidence)
+        ));
+    }
+
+    #[test]
+    fn recognize_with_prior() {
+        let detector = LanguageDetector::with_thresholds(0.5, 0.0).unwrap();
+        assert_eq!(
+            Language::Ru,
+            detector
+                .recognize_with_prior("привет, как дела", Language::En, 0.7)
+                .unwrap()
+        );
+        assert!(matches!(
+            detector.recognize_with_prior("123", Language::En, 0.7),
+            Err(LanguageError::LowConfidence)
+        ));
+        assert!(detector
+            .recognize_with_prior("hi", Language::En, 1.5)
+            .is_err());
+    }
+
+    #[test]
+    fn recognize_with_prior_distance() {
+        // English is more likely by the text, but the prior makes Russian almost as likely.
+        let text = "a б";
+        let detector = LanguageDetector::with_thresholds(0.5, 0.0).unwrap();
+        assert_eq!(
+            Language::En,
+            detector
+                .recognize_with_prior(text, Language::Ru, 0.8)
+                .unwrap()
+        );
+        let detector = LanguageDetector::with_thresholds(0.5, 0.3).unwrap();
+        assert!(matches!(
+            detector.recognize_with_prior(text, Language::Ru, 0.8),
+            Err(LanguageError::LowConfidence)
+        ));
+        assert_eq!(
+            Language::En,
+            detector
+                .recognize_with_prior(text, Language::En, 0.8)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn bigram_score() {
+        let scorer = BigramScorer::for_language(Language::Ru).unwrap();
+        assert_eq!(Some(1.0), scorer.score("Привет, всё!"));
+        assert_eq!(Some(0.0), scorer.score("щъ"));
+        assert_eq!(Some(0.0), scorer.score("ghbdtn"));
+        assert_eq!(None, scorer.score("1, 2"));
+    }
+
+    #[test]
+    fn thresholds_out_of_range() {
+        assert!(LanguageDetector::with_thresholds(1.5, 0.0).is_err());
+        assert!(LanguageDetector::with_thresholds(0.5, 1.0).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+pub mod action;
 pub mod algorithm;
 pub mod keyboard;
 pub mod language;
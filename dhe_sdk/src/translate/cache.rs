@@ -0,0 +1,414 @@
+use std::{
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use lru::LruCache;
+use tracing::{debug, warn};
+
+use super::{TranslateError, Translation, Translator};
+use crate::language::Language;
+
+/// Name of the tree with the keys ordered by creation time, used for eviction.
+const CREATED_TREE: &str = "created";
+
+/// Translation cache settings.
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// Maximum number of entries both in memory and on disk.
+    pub capacity: usize,
+    /// Time after which an entry is considered outdated.
+    pub ttl: Duration,
+    /// Path to the persistent storage, `None` to keep entries in memory only.
+    pub path: Option<PathBuf>,
+}
+
+impl CacheConfig {
+    /// Default path to the persistent storage in the XDG data directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("dhe").join("translation_cache"))
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1000,
+            ttl: Duration::from_secs(30 * 24 * 60 * 60),
+            path: Self::default_path(),
+        }
+    }
+}
+
+/// Number of cache hits and misses since the start.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Translator that caches the results of the inner translator by (text, from, to).
+/// Entries are kept in memory with LRU eviction and are also persisted on disk,
+/// where the oldest created entries are evicted first.
+pub struct CachedTranslator<T> {
+    inner: T,
+    memory: Mutex<LruCache<Vec<u8>, (u64, Translation)>>,
+    storage: Option<Storage>,
+    ttl: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<T: Translator> CachedTranslator<T> {
+    pub fn new(inner: T, config: CacheConfig) -> Result<Self, TranslateError> {
+        let capacity = NonZeroUsize::new(config.capacity)
+            .ok_or_else(|| TranslateError::Cache("capacity must be positive".to_string()))?;
+        let storage = config
+            .path
+            .map(|path| Storage::open(path, config.capacity))
+            .transpose()?;
+
+        Ok(Self {
+            inner,
+            memory: Mutex::new(LruCache::new(capacity)),
+            storage,
+            ttl: config.ttl,
+            hits: AtomicU64::default(),
+            misses: AtomicU64::default(),
+        })
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    fn get(&self, key: &[u8]) -> Option<Translation> {
+        let now = now();
+        let mut memory = self.memory.lock().unwrap();
+        if let Some((created, translation)) = memory.get(key) {
+            if !self.is_expired(*created, now) {
+                return Some(translation.clone());
+            }
+            memory.pop(key);
+        }
+
+        let storage = self.storage.as_ref()?;
+        let (created, translation) = storage.get(key).unwrap_or_else(|err| {
+            warn!("failed to read translation cache: {err}");
+            None
+        })?;
+        if self.is_expired(created, now) {
+            if let Err(err) = storage.remove(key) {
+                warn!("failed to remove outdated translation from cache: {err}");
+            }
+            return None;
+        }
+        memory.put(key.to_vec(), (created, translation.clone()));
+        Some(translation)
+    }
+
+    fn insert(&self, key: Vec<u8>, translation: Translation) {
+        let created = now();
+        if let Some(storage) = &self.storage {
+            if let Err(err) = storage.insert(&key, created, &translation) {
+                warn!("failed to write translation cache: {err}");
+            }
+        }
+        self.memory.lock().unwrap().put(key, (created, translation));
+    }
+
+    fn is_expired(&self, created: u64, now: u64) -> bool {
+        now.saturating_sub(created) > self.ttl.as_secs()
+    }
+}
+
+#[async_trait]
+impl<T: Translator> Translator for CachedTranslator<T> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn translate(
+        &self,
+        text: &str,
+        from: Language,
+        to: Language,
+    ) -> Result<Translation, TranslateError> {
+        let key = format!("{from}\0{to}\0{text}").into_bytes();
+        if let Some(translation) = self.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            debug!("translation cache hit for {text:?}");
+            return Ok(translation);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let translation = self.inner.translate(text, from, to).await?;
+        self.insert(key, translation.clone());
+        Ok(translation)
+    }
+}
+
+/// Persistent part of the cache.
+/// Values are the big-endian creation time in seconds followed by the translation in JSON.
+/// Entries are evicted by creation time, reading an entry doesn't keep it longer.
+struct Storage {
+    entries: sled::Db,
+    created: sled::Tree,
+    capacity: usize,
+    /// Number of the entries, counting them in sled takes a pass over the whole tree.
+    len: AtomicUsize,
+}
+
+impl Storage {
+    fn open(path: PathBuf, capacity: usize) -> Result<Self, TranslateError> {
+        let err_gen = |err: sled::Error| TranslateError::Cache(format!("{path:?}: {err}"));
+        let entries = sled::open(&path).map_err(err_gen)?;
+        let created = entries.open_tree(CREATED_TREE).map_err(err_gen)?;
+        let storage = Self {
+            entries,
+            created,
+            capacity,
+            len: AtomicUsize::default(),
+        };
+        storage.remove_stale().map_err(err_gen)?;
+        storage.len.store(storage.entries.len(), Ordering::Relaxed);
+        Ok(storage)
+    }
+
+    /// Remove the entries that can't be decoded, e.g. written in an older format,
+    /// and the index keys that don't point to an entry created at their time,
+    /// so that eviction never removes a fresh entry by a stale index key.
+    fn remove_stale(&self) -> sled::Result<()> {
+        for entry in self.entries.iter() {
+            let (key, value) = entry?;
+            if decode(&value).is_none() {
+                self.entries.remove(key)?;
+            }
+        }
+        for index_key in self.created.iter().keys() {
+            let index_key = index_key?;
+            let key = index_key.get(8..).unwrap_or_default();
+            let valid = self
+                .get(key)?
+                .is_some_and(|(created, _)| created_key(created, key) == *index_key);
+            if !valid {
+                self.created.remove(index_key)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self, key: &[u8]) -> sled::Result<Option<(u64, Translation)>> {
+        Ok(self.entries.get(key)?.and_then(|value| decode(&value)))
+    }
+
+    fn insert(&self, key: &[u8], created: u64, translation: &Translation) -> sled::Result<()> {
+        let mut value = created.to_be_bytes().to_vec();
+        serde_json::to_writer(&mut value, translation)
+            .map_err(|err| sled::Error::Unsupported(err.to_string()))?;
+        match self.entries.insert(key, value)? {
+            Some(previous) => {
+                if let Some((previous_created, _)) = decode(&previous) {
+                    self.created.remove(created_key(previous_created, key))?;
+                }
+            }
+            None => {
+                self.len.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.created.insert(created_key(created, key), &[])?;
+
+        while self.len.load(Ordering::Relaxed) > self.capacity {
+            let Some((oldest, _)) = self.created.pop_min()? else {
+                break;
+            };
+            if self.entries.remove(&oldest[8..])?.is_some() {
+                self.len.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+        Ok(())
+    }
+
+    fn remove(&self, key: &[u8]) -> sled::Result<()> {
+        if let Some(value) = self.entries.remove(key)? {
+            self.len.fetch_sub(1, Ordering::Relaxed);
+            if let Some((created, _)) = decode(&value) {
+                self.created.remove(created_key(created, key))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn created_key(created: u64, key: &[u8]) -> Vec<u8> {
+    let mut created_key = created.to_be_bytes().to_vec();
+    created_key.extend_from_slice(key);
+    created_key
+}
+
+fn decode(value: &[u8]) -> Option<(u64, Translation)> {
+    let created = u64::from_be_bytes(value.get(..8)?.try_into().ok()?);
+    let translation = serde_json::from_slice(&value[8..]).ok()?;
+    Some((created, translation))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, sync::Arc};
+
+    use super::*;
+
+    /// Translator that counts its calls and fails after the first one.
+    #[derive(Clone, Default)]
+    struct OnceTranslator(Arc<AtomicU64>);
+
+    #[async_trait]
+    impl Translator for OnceTranslator {
+        fn name(&self) -> &str {
+            "once"
+        }
+
+        async fn translate(
+            &self,
+            text: &str,
+            _: Language,
+            _: Language,
+        ) -> Result<Translation, TranslateError> {
+            if self.0.fetch_add(1, Ordering::Relaxed) > 0 {
+                return Err(TranslateError::FailedToRequest("offline".to_string()));
+            }
+            Ok(Translation::new(text.to_uppercase(), self.name()))
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("dhe_cache_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn cache_hit() {
+        let config = CacheConfig {
+            path: None,
+            ..Default::default()
+        };
+        let translator = CachedTranslator::new(OnceTranslator::default(), config).unwrap();
+
+        for _ in 0..2 {
+            let translation = translator
+                .translate("hello", Language::En, Language::Ru)
+                .await;
+            assert_eq!("HELLO", translation.unwrap().text);
+        }
+        assert_eq!(CacheStats { hits: 1, misses: 1 }, translator.stats());
+    }
+
+    #[tokio::test]
+    async fn cache_persists() {
+        let path = temp_path("persists");
+        let config = CacheConfig {
+            path: Some(path.clone()),
+            ..Default::default()
+        };
+
+        let inner = OnceTranslator::default();
+        {
+            let translator = CachedTranslator::new(inner.clone(), config.clone()).unwrap();
+            let translation = translator
+                .translate("hello", Language::En, Language::Ru)
+                .await;
+            assert_eq!("HELLO", translation.unwrap().text);
+        }
+
+        let translator = CachedTranslator::new(inner, config).unwrap();
+        let translation = translator
+            .translate("hello", Language::En, Language::Ru)
+            .await;
+        assert_eq!("HELLO", translation.unwrap().text);
+        assert_eq!(CacheStats { hits: 1, misses: 0 }, translator.stats());
+
+        drop(translator);
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cache_expires() {
+        let config = CacheConfig {
+            ttl: Duration::ZERO,
+            path: None,
+            ..Default::default()
+        };
+        let translator = CachedTranslator::new(OnceTranslator::default(), config).unwrap();
+        translator.memory.lock().unwrap().put(
+            b"en\0ru\0hello".to_vec(),
+            (now() - 1, Translation::new("OUTDATED", "once")),
+        );
+
+        let translation = translator
+            .translate("hello", Language::En, Language::Ru)
+            .await;
+        assert_eq!("HELLO", translation.unwrap().text);
+        assert_eq!(CacheStats { hits: 0, misses: 1 }, translator.stats());
+    }
+
+    #[test]
+    fn stale_entries() {
+        let path = temp_path("stale");
+        let translation = |text| Translation::new(text, "once");
+        {
+            let storage = Storage::open(path.clone(), 2).unwrap();
+            // Entry of the format before JSON translations, with its index key.
+            let mut value = 1u64.to_be_bytes().to_vec();
+            value.extend_from_slice(b"A");
+            storage.entries.insert(b"a", value).unwrap();
+            storage.created.insert(created_key(1, b"a"), &[]).unwrap();
+            storage.insert(b"b", 2, &translation("B")).unwrap();
+        }
+
+        let storage = Storage::open(path.clone(), 2).unwrap();
+        assert_eq!(None, storage.get(b"a").unwrap());
+        assert_eq!(1, storage.len.load(Ordering::Relaxed));
+        assert_eq!(1, storage.created.len());
+        storage.insert(b"a", 3, &translation("A")).unwrap();
+        storage.insert(b"c", 4, &translation("C")).unwrap();
+        assert_eq!(Some((3, translation("A"))), storage.get(b"a").unwrap());
+        assert_eq!(None, storage.get(b"b").unwrap());
+
+        drop(storage);
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn storage_eviction() {
+        let path = temp_path("eviction");
+        let storage = Storage::open(path.clone(), 2).unwrap();
+        let translation = |text| Translation::new(text, "once");
+        storage.insert(b"a", 1, &translation("A")).unwrap();
+        storage.insert(b"b", 2, &translation("B")).unwrap();
+        storage.insert(b"c", 3, &translation("C")).unwrap();
+
+        assert_eq!(None, storage.get(b"a").unwrap());
+        assert_eq!(Some((2, translation("B"))), storage.get(b"b").unwrap());
+        assert_eq!(Some((3, translation("C"))), storage.get(b"c").unwrap());
+        assert_eq!(2, storage.len.load(Ordering::Relaxed));
+
+        drop(storage);
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+
+use super::{TranslateError, Translation, Translator};
+use crate::language::Language;
+
+const DEFAULT_URL: &str = "https://api-free.deepl.com";
+
+/// Translation of text through a DeepL-compatible HTTP API.
+pub struct DeeplTranslator {
+    client: Client,
+    url: Url,
+    auth_key: String,
+}
+
+impl DeeplTranslator {
+    pub fn new<S: Into<String>>(auth_key: S) -> Self {
+        Self::with_url(Url::parse(DEFAULT_URL).unwrap(), auth_key)
+    }
+
+    pub fn with_url<S: Into<String>>(url: Url, auth_key: S) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+            auth_key: auth_key.into(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Response {
+    translations: Vec<ResponseTranslation>,
+}
+
+#[derive(Deserialize)]
+struct ResponseTranslation {
+    detected_source_language: Option<String>,
+    text: String,
+}
+
+#[async_trait]
+impl Translator for DeeplTranslator {
+    fn name(&self) -> &str {
+        "deepl"
+    }
+
+    async fn translate(
+        &self,
+        text: &str,
+        from: Language,
+        to: Language,
+    ) -> Result<Translation, TranslateError> {
+        let url = self
+            .url
+            .join("v2/translate")
+            .map_err(|err| TranslateError::FailedToRequest(err.to_string()))?;
+        let source_lang = from.to_string().to_uppercase();
+        let target_lang = to.to_string().to_uppercase();
+
+        let response: Response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("DeepL-Auth-Key {}", self.auth_key))
+            .form(&[
+                ("text", text),
+                ("source_lang", &source_lang),
+                ("target_lang", &target_lang),
+            ])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| TranslateError::FailedToRequest(err.to_string()))?
+            .json()
+            .await
+            .map_err(|err| TranslateError::FailedToParseRequest(err.to_string()))?;
+
+        response
+            .translations
+            .into_iter()
+            .next()
+            .map(
+                |ResponseTranslation {
+                     detected_source_language,
+                     text,
+                 }| {
+                    let mut translation = Translation::new(text, self.name());
+                    translation.detected_language =
+                        detected_source_language.and_then(|language| language.parse().ok());
+                    translation
+                },
+            )
+            .ok_or(TranslateError::TranslationNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translate::mock_server::serve_once;
+
+    #[tokio::test]
+    async fn translate() {
+        let body = r#"{"translations": [{"detected_source_language": "EN", "text": "привет"}]}"#;
+        let (url, request) = serve_once(200, body).await;
+
+        let translator = DeeplTranslator::with_url(url, "secret");
+        let translation = translator
+            .translate("hello", Language::En, Language::Ru)
+            .await;
+        let translation = translation.unwrap();
+        assert_eq!("привет", translation.text);
+        assert_eq!(Some(Language::En), translation.detected_language);
+
+        let request = request.await.unwrap().to_lowercase();
+        assert!(request.starts_with("post /v2/translate "));
+        assert!(request.contains("authorization: deepl-auth-key secret"));
+        assert!(request.ends_with("text=hello&source_lang=en&target_lang=ru"));
+    }
+
+    #[tokio::test]
+    async fn translate_with_empty_response() {
+        let (url, _) = serve_once(200, r#"{"translations": []}"#).await;
+
+        let translator = DeeplTranslator::with_url(url, "secret");
+        let translation = translator
+            .translate("hello", Language::En, Language::Ru)
+            .await;
+        assert!(matches!(
+            translation,
+            Err(TranslateError::TranslationNotFound)
+        ));
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt::Display;
+
+/// Abbreviations of parts of speech found in En/Ru dictionaries.
+const PARTS_OF_SPEECH: &[&str] = &[
+    "n",
+    "noun",
+    "v",
+    "verb",
+    "adj",
+    "adjective",
+    "adv",
+    "adverb",
+    "pron",
+    "pronoun",
+    "prep",
+    "preposition",
+    "conj",
+    "conjunction",
+    "int",
+    "interj",
+    "interjection",
+    "num",
+    "numeral",
+    "сущ",
+    "гл",
+    "прил",
+    "нареч",
+    "мест",
+    "предл",
+    "союз",
+    "межд",
+    "числ",
+    "част",
+];
+
+/// Dictionary article of a single headword.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DictionaryEntry {
+    pub headword: String,
+    pub part_of_speech: Option<String>,
+    pub translations: Vec<String>,
+    pub examples: Vec<String>,
+}
+
+impl DictionaryEntry {
+    /// Parsing of a plain text (or simple html) article.
+    /// Every line is either a part of speech marker, an example in the form "phrase — translation",
+    /// or a list of translations separated by commas and semicolons.
+    pub fn parse(headword: &str, article: &str) -> Self {
+        let mut entry = DictionaryEntry {
+            headword: headword.to_string(),
+            ..Default::default()
+        };
+
+        for line in strip_tags(article).lines().map(str::trim) {
+            if line.is_empty() || line == headword {
+                continue;
+            }
+            if let Some(pos) = part_of_speech(line) {
+                entry.part_of_speech.get_or_insert(pos);
+            } else if line.contains(" — ") || line.contains(" - ") {
+                entry.examples.push(line.to_string());
+            } else {
+                entry.translations.extend(
+                    strip_numbering(line)
+                        .split([',', ';'])
+                        .map(str::trim)
+                        .filter(|translation| !translation.is_empty())
+                        .map(str::to_string),
+                );
+            }
+        }
+        entry
+    }
+
+    /// Parsing of an XDXF article, where the parts are marked up with
+    /// `<gr>` (part of speech), `<dtrn>` (translation) and `<ex>` (example) tags.
+    pub fn parse_xdxf(headword: &str, article: &str) -> Self {
+        let translations = tag_contents(article, "dtrn");
+        if translations.is_empty() {
+            return Self::parse(headword, article);
+        }
+
+        DictionaryEntry {
+            headword: headword.to_string(),
+            part_of_speech: tag_contents(article, "gr")
+                .into_iter()
+                .next()
+                .map(|pos| pos.trim_end_matches('.').to_string()),
+            translations,
+            examples: tag_contents(article, "ex"),
+        }
+    }
+}
+
+impl Display for DictionaryEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.translations.join(", "))?;
+        if let Some(pos) = &self.part_of_speech {
+            write!(f, " ({pos})")?;
+        }
+        for example in &self.examples {
+            write!(f, "\n{example}")?;
+        }
+        Ok(())
+    }
+}
+
+fn part_of_speech(line: &str) -> Option<String> {
+    let pos = line.trim_matches(|c: char| matches!(c, '_' | '[' | ']' | '(' | ')' | '.' | ' '));
+    PARTS_OF_SPEECH
+        .contains(&pos.to_lowercase().as_str())
+        .then(|| pos.to_string())
+}
+
+/// Removal of list numbering like "1." or "2)".
+fn strip_numbering(line: &str) -> &str {
+    let rest = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    if rest.len() != line.len() {
+        if let Some(rest) = rest.strip_prefix(['.', ')']) {
+            return rest.trim_start();
+        }
+    }
+    line
+}
+
+/// Replacement of html line breaks with new lines and removal of all other tags.
+fn strip_tags(article: &str) -> String {
+    let article = article
+        .replace("<br>", "\n")
+        .replace("<br/>", "\n")
+        .replace("<br />", "\n");
+    let mut text = String::with_capacity(article.len());
+    let mut in_tag = false;
+    for c in article.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Text contents of all the given tags.
+fn tag_contents(article: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    let mut contents = vec![];
+    let mut rest = article;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        let content = strip_tags(&rest[..end]).trim().to_string();
+        if !content.is_empty() {
+            contents.push(content);
+        }
+        rest = &rest[end + close.len()..];
+    }
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_plain() {
+        let article = "_межд.\n1. привет, здравствуй\n2) алло\nhello there — привет";
+        let entry = DictionaryEntry::parse("hello", article);
+        assert_eq!(Some("межд".to_string()), entry.part_of_speech);
+        assert_eq!(vec!["привет", "здравствуй", "алло"], entry.translations);
+        assert_eq!(vec!["hello there — привет"], entry.examples);
+    }
+
+    #[test]
+    fn parse_xdxf() {
+        let article = "<k>run</k> <gr>v.</gr> <dtrn>бежать</dtrn>; <dtrn>управлять</dtrn>\
+            <ex>run a company — управлять компанией</ex>";
+        let entry = DictionaryEntry::parse_xdxf("run", article);
+        assert_eq!(Some("v".to_string()), entry.part_of_speech);
+        assert_eq!(vec!["бежать", "управлять"], entry.translations);
+        assert_eq!(vec!["run a company — управлять компанией"], entry.examples);
+    }
+
+    #[test]
+    fn display() {
+        let entry = DictionaryEntry {
+            headword: "run".to_string(),
+            part_of_speech: Some("v".to_string()),
+            translations: vec!["бежать".to_string(), "управлять".to_string()],
+            examples: vec!["run a company — управлять компанией".to_string()],
+        };
+        assert_eq!(
+            "бежать, управлять (v)\nrun a company — управлять компанией",
+            entry.to_string()
+        );
+    }
+}
@@ -0,0 +1,151 @@
+mod article;
+mod stardict;
+
+pub use article::DictionaryEntry;
+
+use std::{collections::HashMap, fs, path::Path};
+
+use async_trait::async_trait;
+
+use self::stardict::StarDict;
+use super::{TranslateError, Translation, Translator};
+use crate::language::Language;
+
+/// Offline translation of single words through a local bilingual dictionary.
+///
+/// Two dictionary formats are supported:
+/// - StarDict, the path points to the `.ifo` file next to the `.idx` and `.dict` (or `.dict.dz`) files;
+/// - plain text, one entry per line: a headword and its translation separated by a tab.
+pub struct DictionaryTranslator {
+    from: Language,
+    to: Language,
+    storage: Storage,
+}
+
+enum Storage {
+    Plain(HashMap<String, DictionaryEntry>),
+    StarDict(StarDict),
+}
+
+impl DictionaryTranslator {
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        from: Language,
+        to: Language,
+    ) -> Result<Self, TranslateError> {
+        let path = path.as_ref();
+        let storage = if path.extension().is_some_and(|ext| ext == "ifo") {
+            Storage::StarDict(StarDict::load(path)?)
+        } else {
+            let data = fs::read_to_string(path).map_err(|err| {
+                TranslateError::FailedToLoadDictionary(path.to_path_buf(), err.to_string())
+            })?;
+            Storage::Plain(parse_plain(&data))
+        };
+        Ok(Self { from, to, storage })
+    }
+
+    pub fn parse(data: &str, from: Language, to: Language) -> Self {
+        Self {
+            from,
+            to,
+            storage: Storage::Plain(parse_plain(data)),
+        }
+    }
+
+    /// Dictionary entry for the given headword.
+    pub fn lookup(&self, word: &str) -> Option<DictionaryEntry> {
+        let word = normalize(word);
+        match &self.storage {
+            Storage::Plain(entries) => entries.get(&word).cloned(),
+            Storage::StarDict(dict) => dict.lookup(&word),
+        }
+    }
+}
+
+#[async_trait]
+impl Translator for DictionaryTranslator {
+    fn name(&self) -> &str {
+        "dictionary"
+    }
+
+    async fn translate(
+        &self,
+        text: &str,
+        from: Language,
+        to: Language,
+    ) -> Result<Translation, TranslateError> {
+        if from != self.from || to != self.to {
+            return Err(TranslateError::TranslationNotFound);
+        }
+        let entry = self
+            .lookup(text)
+            .ok_or(TranslateError::TranslationNotFound)?;
+        let mut translations = entry.translations.into_iter();
+        let mut translation = Translation::new(
+            translations
+                .next()
+                .ok_or(TranslateError::TranslationNotFound)?,
+            self.name(),
+        );
+        translation.alternatives = translations.collect();
+        translation.part_of_speech = entry.part_of_speech;
+        translation.examples = entry.examples;
+        Ok(translation)
+    }
+}
+
+fn parse_plain(data: &str) -> HashMap<String, DictionaryEntry> {
+    data.lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(word, article)| {
+            (
+                normalize(word),
+                DictionaryEntry::parse(word.trim(), article),
+            )
+        })
+        .collect()
+}
+
+fn normalize(word: &str) -> String {
+    word.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn translate() {
+        let translator = DictionaryTranslator::parse(
+            "hello\tпривет, алло\nworld\tмир\n",
+            Language::En,
+            Language::Ru,
+        );
+        let translation = translator
+            .translate(" Hello ", Language::En, Language::Ru)
+            .await
+            .unwrap();
+        assert_eq!("привет", translation.text);
+        assert_eq!(vec!["алло"], translation.alternatives);
+    }
+
+    #[tokio::test]
+    async fn translate_unknown_word() {
+        let translator = DictionaryTranslator::parse("hello\tпривет\n", Language::En, Language::Ru);
+        let translation = translator
+            .translate("bye", Language::En, Language::Ru)
+            .await;
+        assert!(matches!(
+            translation,
+            Err(TranslateError::TranslationNotFound)
+        ));
+        let translation = translator
+            .translate("hello", Language::Ru, Language::En)
+            .await;
+        assert!(matches!(
+            translation,
+            Err(TranslateError::TranslationNotFound)
+        ));
+    }
+}
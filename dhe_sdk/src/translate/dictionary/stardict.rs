@@ -0,0 +1,244 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+
+use super::{normalize, DictionaryEntry};
+use crate::translate::TranslateError;
+
+const IFO_MAGIC: &str = "StarDict's dict ifo file";
+
+/// Dictionary in the StarDict format.
+/// The `.idx` index and the `.dict` data are loaded into memory, articles are parsed on lookup.
+pub struct StarDict {
+    index: HashMap<String, (String, usize, usize)>,
+    data: Vec<u8>,
+    same_type_sequence: Option<String>,
+}
+
+impl StarDict {
+    /// Loading of the dictionary by the path of its `.ifo` file.
+    pub fn load(ifo_path: &Path) -> Result<Self, TranslateError> {
+        let err_gen =
+            |err: String| TranslateError::FailedToLoadDictionary(ifo_path.to_path_buf(), err);
+
+        let ifo = fs::read_to_string(ifo_path).map_err(|err| err_gen(err.to_string()))?;
+        let mut lines = ifo.lines();
+        if lines.next().map(str::trim) != Some(IFO_MAGIC) {
+            return Err(err_gen("not a StarDict ifo file".to_string()));
+        }
+        let options: HashMap<&str, &str> = lines
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect();
+        let offset_bits = match options.get("idxoffsetbits") {
+            None | Some(&"32") => 32,
+            Some(&"64") => 64,
+            Some(bits) => return Err(err_gen(format!("unsupported idxoffsetbits {bits}"))),
+        };
+
+        let idx = read_maybe_gz(&ifo_path.with_extension("idx")).map_err(err_gen)?;
+        let data = read_maybe_gz(&ifo_path.with_extension("dict")).map_err(err_gen)?;
+
+        Ok(Self {
+            index: parse_idx(&idx, offset_bits).map_err(err_gen)?,
+            data,
+            same_type_sequence: options.get("sametypesequence").map(|s| s.to_string()),
+        })
+    }
+
+    /// Dictionary entry for the given normalized headword.
+    pub fn lookup(&self, word: &str) -> Option<DictionaryEntry> {
+        let (headword, offset, size) = self.index.get(word)?;
+        let article = self.data.get(*offset..offset + size)?;
+        let fields = match &self.same_type_sequence {
+            Some(types) => split_typed_fields(article, types),
+            None => split_fields(article),
+        };
+
+        let xdxf: Vec<&str> = fields
+            .iter()
+            .filter(|(kind, _)| *kind == 'x')
+            .map(|(_, text)| text.as_str())
+            .collect();
+        if !xdxf.is_empty() {
+            return Some(DictionaryEntry::parse_xdxf(headword, &xdxf.join("\n")));
+        }
+
+        let text: Vec<&str> = fields
+            .iter()
+            .filter(|(kind, _)| matches!(kind, 'm' | 'l' | 'g' | 'h' | 'w'))
+            .map(|(_, text)| text.as_str())
+            .collect();
+        Some(DictionaryEntry::parse(headword, &text.join("\n")))
+    }
+}
+
+/// Reading of the file or its gzip (dictzip) compressed version with the `.gz` or `.dz` suffix.
+fn read_maybe_gz(path: &Path) -> Result<Vec<u8>, String> {
+    if path.exists() {
+        return fs::read(path).map_err(|err| format!("{path:?}: {err}"));
+    }
+    for suffix in ["dz", "gz"] {
+        let mut compressed = PathBuf::from(path);
+        compressed.as_mut_os_string().push(format!(".{suffix}"));
+        if compressed.exists() {
+            let file =
+                fs::File::open(&compressed).map_err(|err| format!("{compressed:?}: {err}"))?;
+            let mut data = vec![];
+            GzDecoder::new(file)
+                .read_to_end(&mut data)
+                .map_err(|err| format!("{compressed:?}: {err}"))?;
+            return Ok(data);
+        }
+    }
+    Err(format!("{path:?} not found"))
+}
+
+/// Each index entry is a null-terminated headword followed by the big-endian offset and size of its article.
+fn parse_idx(
+    idx: &[u8],
+    offset_bits: usize,
+) -> Result<HashMap<String, (String, usize, usize)>, String> {
+    let offset_len = offset_bits / 8;
+    let mut index = HashMap::new();
+    let mut rest = idx;
+    while !rest.is_empty() {
+        let end = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or("broken idx file: headword is not terminated")?;
+        let headword = String::from_utf8_lossy(&rest[..end]).into_owned();
+        rest = &rest[end + 1..];
+        if rest.len() < offset_len + 4 {
+            return Err("broken idx file: unexpected end of file".to_string());
+        }
+        let offset = read_be(&rest[..offset_len]);
+        let size = read_be(&rest[offset_len..offset_len + 4]);
+        rest = &rest[offset_len + 4..];
+        index
+            .entry(normalize(&headword))
+            .or_insert((headword, offset, size));
+    }
+    Ok(index)
+}
+
+fn read_be(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |acc, &b| (acc << 8) | b as usize)
+}
+
+/// Splitting of an article with the `sametypesequence` option: type markers are omitted
+/// and the last field occupies the rest of the article.
+fn split_typed_fields(article: &[u8], types: &str) -> Vec<(char, String)> {
+    let mut fields = vec![];
+    let mut rest = article;
+    let types: Vec<char> = types.chars().collect();
+    for (i, &kind) in types.iter().enumerate() {
+        let last = i + 1 == types.len();
+        let Some((data, tail)) = take_field(rest, kind, last) else {
+            break;
+        };
+        fields.push((kind, data));
+        rest = tail;
+    }
+    fields
+}
+
+/// Splitting of an article where every field is prefixed with its type marker.
+fn split_fields(article: &[u8]) -> Vec<(char, String)> {
+    let mut fields = vec![];
+    let mut rest = article;
+    while let Some((&kind, tail)) = rest.split_first() {
+        let kind = kind as char;
+        let Some((data, tail)) = take_field(tail, kind, false) else {
+            break;
+        };
+        fields.push((kind, data));
+        rest = tail;
+    }
+    fields
+}
+
+/// Lowercase types are null-terminated strings, uppercase types are binary data prefixed with their size.
+fn take_field(data: &[u8], kind: char, last: bool) -> Option<(String, &[u8])> {
+    if kind.is_ascii_uppercase() {
+        let size = read_be(data.get(..4)?);
+        let tail = data.get(4 + size..)?;
+        return Some((String::new(), tail));
+    }
+    let (field, tail) = match data.iter().position(|&b| b == 0) {
+        Some(end) if !last => (&data[..end], &data[end + 1..]),
+        _ => (data, &data[data.len()..]),
+    };
+    Some((String::from_utf8_lossy(field).into_owned(), tail))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn write_dictionary(name: &str, ifo_options: &str, articles: &[(&str, &[u8])]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("dhe_stardict_{name}_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut idx = vec![];
+        let mut dict = vec![];
+        for (word, article) in articles {
+            idx.extend_from_slice(word.as_bytes());
+            idx.push(0);
+            idx.extend_from_slice(&(dict.len() as u32).to_be_bytes());
+            idx.extend_from_slice(&(article.len() as u32).to_be_bytes());
+            dict.extend_from_slice(article);
+        }
+
+        let ifo_path = dir.join("test.ifo");
+        fs::write(
+            &ifo_path,
+            format!(
+                "{IFO_MAGIC}\nversion=2.4.2\nwordcount={}\n{ifo_options}",
+                articles.len()
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("test.idx"), idx).unwrap();
+        fs::write(dir.join("test.dict"), dict).unwrap();
+        ifo_path
+    }
+
+    #[test]
+    fn lookup_same_type_sequence() {
+        let ifo_path = write_dictionary(
+            "sequence",
+            "sametypesequence=m\n",
+            &[
+                ("Hello", "_межд.\nпривет, здравствуй".as_bytes()),
+                ("world", "мир".as_bytes()),
+            ],
+        );
+        let dict = StarDict::load(&ifo_path).unwrap();
+
+        let entry = dict.lookup("hello").unwrap();
+        assert_eq!("Hello", entry.headword);
+        assert_eq!(Some("межд".to_string()), entry.part_of_speech);
+        assert_eq!(vec!["привет", "здравствуй"], entry.translations);
+        assert_eq!(vec!["мир"], dict.lookup("world").unwrap().translations);
+        assert!(dict.lookup("bye").is_none());
+    }
+
+    #[test]
+    fn lookup_typed_fields() {
+        let article = "t[ran]\0x<gr>v.</gr> <dtrn>бежать</dtrn>\0".as_bytes();
+        let ifo_path = write_dictionary("typed", "", &[("run", article)]);
+        let dict = StarDict::load(&ifo_path).unwrap();
+
+        let entry = dict.lookup("run").unwrap();
+        assert_eq!(Some("v".to_string()), entry.part_of_speech);
+        assert_eq!(vec!["бежать"], entry.translations);
+    }
+}
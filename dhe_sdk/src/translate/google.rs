@@ -0,0 +1,114 @@
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use scraper::{Html, Selector};
+
+use super::{TranslateError, Translation, Translator};
+use crate::language::Language;
+
+const DEFAULT_URL: &str = "https://translate.google.com/m";
+
+/// Translation of text through the mobile version of google translator.
+pub struct GoogleTranslator {
+    client: Client,
+    url: Url,
+}
+
+impl GoogleTranslator {
+    pub fn new() -> Self {
+        Self::with_url(Url::parse(DEFAULT_URL).unwrap())
+    }
+
+    pub fn with_url(url: Url) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+        }
+    }
+}
+
+impl Default for GoogleTranslator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Translator for GoogleTranslator {
+    fn name(&self) -> &str {
+        "google"
+    }
+
+    async fn translate(
+        &self,
+        text: &str,
+        from: Language,
+        to: Language,
+    ) -> Result<Translation, TranslateError> {
+        let mut url = self.url.clone();
+        url.query_pairs_mut()
+            .append_pair("sl", &from.to_string())
+            .append_pair("tl", &to.to_string())
+            .append_pair("q", text);
+
+        let html = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| TranslateError::FailedToRequest(err.to_string()))?
+            .text()
+            .await
+            .map_err(|err| TranslateError::FailedToParseRequest(err.to_string()))?;
+
+        let fragment = Html::parse_fragment(&html);
+        let selector = Selector::parse(".result-container").unwrap();
+        let element = fragment.select(&selector).next().ok_or_else(|| {
+            TranslateError::FailedToParseRequest(
+                "translation not found as a result of the query".to_string(),
+            )
+        })?;
+        Ok(Translation::new(
+            element.text().collect::<String>(),
+            self.name(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translate::mock_server::serve_once;
+
+    #[tokio::test]
+    async fn translate() {
+        let body =
+            r#"<html><div class="result-container">&quot;привет&quot; &amp; пока</div></html>"#;
+        let (url, request) = serve_once(200, body).await;
+
+        let translator = GoogleTranslator::with_url(url);
+        let translation = translator
+            .translate("hello", Language::En, Language::Ru)
+            .await;
+        let translation = translation.unwrap();
+        assert_eq!("\"привет\" & пока", translation.text);
+        assert_eq!("google", translation.backend);
+
+        let request = request.await.unwrap();
+        assert!(request.starts_with("GET /?sl=en&tl=ru&q=hello "));
+    }
+
+    #[tokio::test]
+    async fn translate_without_result() {
+        let (url, _) = serve_once(200, "<html></html>").await;
+
+        let translator = GoogleTranslator::with_url(url);
+        let translation = translator
+            .translate("hello", Language::En, Language::Ru)
+            .await;
+        assert!(matches!(
+            translation,
+            Err(TranslateError::FailedToParseRequest(_))
+        ));
+    }
+}
@@ -0,0 +1,145 @@
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+
+use super::{TranslateError, Translation, Translator};
+use crate::language::Language;
+
+const MAX_ALTERNATIVES: usize = 3;
+
+/// Translation of text through a LibreTranslate server.
+pub struct LibreTranslator {
+    client: Client,
+    url: Url,
+    api_key: Option<String>,
+}
+
+impl LibreTranslator {
+    pub fn new(url: Url, api_key: Option<String>) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+            api_key,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    q: &'a str,
+    source: String,
+    target: String,
+    format: &'static str,
+    alternatives: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    translated_text: String,
+    #[serde(default)]
+    alternatives: Vec<String>,
+    detected_language: Option<DetectedLanguage>,
+}
+
+#[derive(Deserialize)]
+struct DetectedLanguage {
+    /// Confidence in percent.
+    confidence: f64,
+    language: String,
+}
+
+#[async_trait]
+impl Translator for LibreTranslator {
+    fn name(&self) -> &str {
+        "libre-translate"
+    }
+
+    async fn translate(
+        &self,
+        text: &str,
+        from: Language,
+        to: Language,
+    ) -> Result<Translation, TranslateError> {
+        let url = self
+            .url
+            .join("translate")
+            .map_err(|err| TranslateError::FailedToRequest(err.to_string()))?;
+        let request = Request {
+            q: text,
+            source: from.to_string(),
+            target: to.to_string(),
+            format: "text",
+            alternatives: MAX_ALTERNATIVES,
+            api_key: self.api_key.as_deref(),
+        };
+
+        let response: Response = self
+            .client
+            .post(url)
+            .json(&request)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| TranslateError::FailedToRequest(err.to_string()))?
+            .json()
+            .await
+            .map_err(|err| TranslateError::FailedToParseRequest(err.to_string()))?;
+
+        let mut translation = Translation::new(response.translated_text, self.name());
+        translation.alternatives = response.alternatives;
+        if let Some(detected) = response.detected_language {
+            translation.detected_language = detected.language.parse().ok();
+            translation.confidence = Some(detected.confidence / 100.0);
+        }
+        Ok(translation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translate::mock_server::serve_once;
+
+    #[tokio::test]
+    async fn translate() {
+        let body = r#"{
+            "translatedText": "привет",
+            "alternatives": ["здравствуйте"],
+            "detectedLanguage": {"confidence": 90.0, "language": "en"}
+        }"#;
+        let (url, request) = serve_once(200, body).await;
+
+        let translator = LibreTranslator::new(url, Some("secret".to_string()));
+        let translation = translator
+            .translate("hello", Language::En, Language::Ru)
+            .await;
+        let translation = translation.unwrap();
+        assert_eq!("привет", translation.text);
+        assert_eq!(vec!["здравствуйте"], translation.alternatives);
+        assert_eq!(Some(Language::En), translation.detected_language);
+        assert_eq!(Some(0.9), translation.confidence);
+
+        let request = request.await.unwrap();
+        assert!(request.starts_with("POST /translate "));
+        assert!(request.ends_with(
+            r#"{"q":"hello","source":"en","target":"ru","format":"text","alternatives":3,"api_key":"secret"}"#
+        ));
+    }
+
+    #[tokio::test]
+    async fn translate_with_error_status() {
+        let (url, _) = serve_once(403, r#"{"error": "Invalid API key"}"#).await;
+
+        let translator = LibreTranslator::new(url, None);
+        let translation = translator
+            .translate("hello", Language::En, Language::Ru)
+            .await;
+        assert!(matches!(
+            translation,
+            Err(TranslateError::FailedToRequest(_))
+        ));
+    }
+}
@@ -0,0 +1,175 @@
+pub mod cache;
+pub mod deepl;
+pub mod dictionary;
+pub mod google;
+pub mod libre;
+#[cfg(test)]
+mod mock_server;
+
+pub use cache::{CacheConfig, CacheStats, CachedTranslator};
+pub use deepl::DeeplTranslator;
+pub use dictionary::DictionaryTranslator;
+pub use google::GoogleTranslator;
+pub use libre::LibreTranslator;
+pub use reqwest::Url;
+
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::language::Language;
+
+#[derive(Error, Debug)]
+pub enum TranslateError {
+    #[error("failed to complete request with error: {0}")]
+    FailedToRequest(String),
+    #[error("failed to parse request with error: {0}")]
+    FailedToParseRequest(String),
+    #[error("translation not found")]
+    TranslationNotFound,
+    #[error("failed to load dictionary at {0} path: {1}")]
+    FailedToLoadDictionary(PathBuf, String),
+    #[error("translation cache error: {0}")]
+    Cache(String),
+    #[error("all translation backends failed: {0:?}")]
+    AllBackendsFailed(Vec<String>),
+}
+
+/// Result of a translation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Translation {
+    /// Primary translation as plain text.
+    pub text: String,
+    /// Alternative translations.
+    pub alternatives: Vec<String>,
+    pub part_of_speech: Option<String>,
+    /// Usage examples in the form "phrase — translation".
+    pub examples: Vec<String>,
+    /// Source language detected by the backend.
+    pub detected_language: Option<Language>,
+    /// Confidence of the detected language from 0 to 1.
+    pub confidence: Option<f64>,
+    /// Name of the backend that produced the translation.
+    pub backend: String,
+}
+
+impl Translation {
+    pub fn new<T: Into<String>, B: Into<String>>(text: T, backend: B) -> Self {
+        Self {
+            text: text.into(),
+            alternatives: vec![],
+            part_of_speech: None,
+            examples: vec![],
+            detected_language: None,
+            confidence: None,
+            backend: backend.into(),
+        }
+    }
+}
+
+/// Translation backend.
+#[async_trait]
+pub trait Translator: Send + Sync {
+    /// Backend name for logs and error messages.
+    fn name(&self) -> &str;
+
+    /// Translation of text from one language to another.
+    async fn translate(
+        &self,
+        text: &str,
+        from: Language,
+        to: Language,
+    ) -> Result<Translation, TranslateError>;
+}
+
+/// Translator that tries the given backends in order until one of them succeeds.
+pub struct FallbackTranslator {
+    backends: Vec<Box<dyn Translator>>,
+}
+
+impl FallbackTranslator {
+    pub fn new(backends: Vec<Box<dyn Translator>>) -> Self {
+        Self { backends }
+    }
+}
+
+#[async_trait]
+impl Translator for FallbackTranslator {
+    fn name(&self) -> &str {
+        "fallback"
+    }
+
+    async fn translate(
+        &self,
+        text: &str,
+        from: Language,
+        to: Language,
+    ) -> Result<Translation, TranslateError> {
+        let mut errors = vec![];
+        for backend in &self.backends {
+            match backend.translate(text, from, to).await {
+                Ok(translation) => return Ok(translation),
+                Err(err) => {
+                    warn!("translation backend {} failed: {err}", backend.name());
+                    errors.push(format!("{}: {err}", backend.name()));
+                }
+            }
+        }
+        Err(TranslateError::AllBackendsFailed(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StaticTranslator(Result<&'static str, ()>);
+
+    #[async_trait]
+    impl Translator for StaticTranslator {
+        fn name(&self) -> &str {
+            "static"
+        }
+
+        async fn translate(
+            &self,
+            _: &str,
+            _: Language,
+            _: Language,
+        ) -> Result<Translation, TranslateError> {
+            self.0
+                .map(|text| Translation::new(text, "static"))
+                .map_err(|_| TranslateError::TranslationNotFound)
+        }
+    }
+
+    #[tokio::test]
+    async fn fallback_uses_next_backend() {
+        let translator = FallbackTranslator::new(vec![
+            Box::new(StaticTranslator(Err(()))),
+            Box::new(StaticTranslator(Ok("привет"))),
+        ]);
+        let translation = translator
+            .translate("hello", Language::En, Language::Ru)
+            .await;
+        assert_eq!("привет", translation.unwrap().text);
+    }
+
+    #[tokio::test]
+    async fn fallback_collects_errors() {
+        let translator = FallbackTranslator::new(vec![
+            Box::new(StaticTranslator(Err(()))),
+            Box::new(StaticTranslator(Err(()))),
+        ]);
+        let translation = translator
+            .translate("hello", Language::En, Language::Ru)
+            .await;
+        assert!(matches!(
+            translation,
+            Err(TranslateError::AllBackendsFailed(errors)) if errors.len() == 2
+        ));
+    }
+}
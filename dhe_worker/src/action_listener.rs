@@ -1,153 +1,668 @@
+use std::{
+    future, io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
 use anyhow::bail;
 use arboard::{Clipboard, GetExtLinux, LinuxClipboardKind};
+use async_trait::async_trait;
 use dhe_sdk::{
-    keyboard::{Key, KeyboardEmulator, KeyboardListener},
-    language::{Language, LanguageDetector},
-    translate::translate,
+    action::{Action, ActionContext, ActionError, ActionRegistry},
+    keyboard::{
+        self, DeviceFilter, Key, KeySequence, KeyboardEmulator, KeyboardError, KeyboardLayout,
+        KeyboardListener, LayoutTracker, Trigger, WordBuffer, LAYOUTS,
+    },
+    language::{Language, LanguageDetector, LanguageError},
+    translate::{CachedTranslator, FallbackTranslator, Translation, Translator},
 };
 use notify_rust::Notification;
-use std::{process::Command, time::Duration};
-use tokio::time::sleep;
-use tracing::{error, warn};
+use serde::Deserialize;
+use tokio::{sync::Mutex, time::sleep};
+use tracing::{debug, error, info, warn};
 
-pub struct ActionListenerParam<N> {
-    pub name: N,
-    pub keys: Vec<Key>,
+use crate::{cli_command::CliCommandsConfig, config_watcher::ConfigWatcher};
+
+pub struct ActionListenerParam {
+    pub name: String,
+    pub keys: KeySequence,
+    pub trigger: Trigger,
+    pub grab: bool,
+    pub action: Box<dyn Action>,
 }
 
-impl<N: AsRef<str>> ActionListenerParam<N> {
+impl ActionListenerParam {
     pub fn validate(&self) -> Result<(), anyhow::Error> {
-        if self.name.as_ref() != TRANSLATE_TO_NOTIFY_ACTION
-            && self.name.as_ref() != TRANSLATE_TO_PASTE_ACTION
-            && self.name.as_ref() != OPEN_GUI_ACTION
-        {
-            bail!("unknown action of the translator")
-        }
-
         if self.keys.is_empty() {
             bail!("empty list of keys")
         }
+        Ok(())
+    }
+}
+
+/// Detection of the language of the selected text.
+/// The language of the active keyboard layout is used as a prior, if known.
+/// If the language is not recognized confidently, the layout language or the preferred language is used.
+pub struct SourceLanguageDetector {
+    detector: LanguageDetector,
+    preferred_language: Option<Language>,
+    layout_weight: f64,
+}
+
+impl SourceLanguageDetector {
+    pub fn new(
+        detector: LanguageDetector,
+        preferred_language: Option<Language>,
+        layout_weight: f64,
+    ) -> Self {
+        Self {
+            detector,
+            preferred_language,
+            layout_weight,
+        }
+    }
+
+    fn detect(&self, text: &str, layout: Option<Language>) -> Result<Language, LanguageError> {
+        debug!(
+            "language confidences: {:?}, active layout: {layout:?}",
+            self.detector.confidences(text)
+        );
+        let recognized = match layout {
+            Some(layout) => self
+                .detector
+                .recognize_with_prior(text, layout, self.layout_weight),
+            None => self.detector.recognize(text),
+        };
+        recognized.or_else(|err| {
+            let lang = layout.or(self.preferred_language).ok_or(err)?;
+            debug!("language of the text is not recognized, using {lang}");
+            Ok(lang)
+        })
+    }
+}
+
+/// The way the text is inserted in place of the selection.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PasteMethod {
+    /// Pasting through the clipboard with Ctrl+V, the clipboard contents are restored afterwards.
+    #[default]
+    Clipboard,
+    /// Typing the text key by key, the clipboard is not touched.
+    Typing,
+}
+
+/// Settings of the text insertion.
+pub struct PasteSettings {
+    pub method: PasteMethod,
+    /// Delay after each typed key.
+    pub key_delay: Duration,
+}
+
+/// Settings of the keyboard listening.
+pub struct ListenerSettings {
+    pub device_filter: DeviceFilter,
+    pub sequence_timeout: Duration,
+    pub layout_tracker: Option<LayoutTracker>,
+    /// Keys that switch the layout when the last word is retyped.
+    pub layout_switch_keys: Vec<Key>,
+}
+
+/// Action bound to keyboard shortcuts.
+#[derive(Clone)]
+struct RegisteredAction {
+    name: String,
+    action: Arc<dyn Action>,
+}
+
+/// Retyping of the last typed word in the other layout.
+struct LastWordRetyper {
+    buffer: WordBuffer,
+    /// Word to retype once the keys of the action are released.
+    pending: Option<WordBuffer>,
+    /// Keys that switch the layout.
+    switch_keys: Vec<Key>,
+}
+
+impl LastWordRetyper {
+    fn new(switch_keys: Vec<Key>) -> Self {
+        Self {
+            buffer: WordBuffer::default(),
+            pending: None,
+            switch_keys,
+        }
+    }
+
+    /// Remember the word typed before the key events of the last step.
+    fn request(&mut self) -> anyhow::Result<()> {
+        if self.switch_keys.is_empty() {
+            bail!("layout switch keys are not configured")
+        }
+        if !self.buffer.is_empty() {
+            self.pending = Some(self.buffer.clone());
+        }
+        Ok(())
+    }
+
+    /// Update the buffer and retype the requested word when the keys are released,
+    /// so that held modifiers do not affect the typed keys.
+    async fn step(
+        &mut self,
+        listener: &mut KeyboardListener<RegisteredAction>,
+        emulator: &Mutex<KeyboardEmulator>,
+    ) -> anyhow::Result<()> {
+        if self.pending.is_none() {
+            self.buffer.apply_events(listener.last_events());
+            return Ok(());
+        }
+        if !listener.is_released() {
+            return Ok(());
+        }
+        let Some(word) = self.pending.take() else {
+            return Ok(());
+        };
 
+        let mut emulator = emulator.lock().await;
+        emulator.backspace(word.len()).await?;
+        emulator.press_combination(&self.switch_keys)?;
+        listener.layout_switched();
+        emulator.type_keys(word.word()).await?;
+        emulator
+            .type_keys(&vec![(Key::Space, false); word.trailing_spaces()])
+            .await?;
+        // The word is kept to be able to switch it back.
+        self.buffer = word;
         Ok(())
     }
 }
 
-const TRANSLATE_TO_NOTIFY_ACTION: &str = "translate-to-notify";
-const TRANSLATE_TO_PASTE_ACTION: &str = "translate-to-paste";
-const OPEN_GUI_ACTION: &str = "open-gui";
+/// Devices, clipboard and settings shared by the actions.
+struct ActionResources {
+    translator: CachedTranslator<FallbackTranslator>,
+    detector: SourceLanguageDetector,
+    emulator: Mutex<KeyboardEmulator>,
+    clipboard: Mutex<Clipboard>,
+    paste_method: PasteMethod,
+    /// Tracked layouts in the XKB group order, the text is typed in one of them.
+    layouts: Vec<Language>,
+    /// Keys emulated to switch to the next layout.
+    layout_switch_keys: Vec<Key>,
+}
 
-pub async fn start_action_listener_loop<N, P>(params: P) -> Result<(), anyhow::Error>
-where
-    P: Iterator<Item = ActionListenerParam<N>>,
-    N: Into<String>,
-{
-    let mut listener = KeyboardListener::new()?;
+/// Context of an action fired while the given layout was active.
+struct WorkerContext {
+    resources: Arc<ActionResources>,
+    layout: Option<Language>,
+    /// Whether the action runs in the listener task, only such actions can retype words.
+    inline: bool,
+    retype_requested: AtomicBool,
+}
 
-    for ActionListenerParam { name, keys } in params {
-        listener.register_action(name, &keys);
+impl WorkerContext {
+    fn new(resources: Arc<ActionResources>, layout: Option<Language>, inline: bool) -> Self {
+        Self {
+            resources,
+            layout,
+            inline,
+            retype_requested: AtomicBool::new(false),
+        }
     }
+}
 
-    let mut emulator = KeyboardEmulator::new()?;
-    let mut clipboard = Clipboard::new()?;
-    let detector = LanguageDetector::new();
+fn failed(context: &str, err: impl std::fmt::Display) -> ActionError {
+    ActionError::Failed(format!("{context}: {err}"))
+}
 
-    loop {
-        if let Err(err) = action_step(&mut listener, &mut emulator, &mut clipboard, &detector).await
-        {
-            error!("translate error: {err}")
+#[async_trait]
+impl ActionContext for WorkerContext {
+    fn layout(&self) -> Option<Language> {
+        self.layout
+    }
+
+    fn detect_language(&self, text: &str) -> Result<Language, ActionError> {
+        self.resources
+            .detector
+            .detect(text, self.layout)
+            .map_err(ActionError::Language)
+    }
+
+    fn fix_layout(&self, text: &str) -> Option<String> {
+        keyboard::fix_layout(text)
+    }
+
+    async fn translate(
+        &self,
+        text: &str,
+        from: Language,
+        to: Language,
+    ) -> Result<Translation, ActionError> {
+        self.resources
+            .translator
+            .translate(text, from, to)
+            .await
+            .map_err(ActionError::Translate)
+    }
+
+    async fn selected_text(&self) -> Result<String, ActionError> {
+        self.resources
+            .clipboard
+            .lock()
+            .await
+            .get()
+            .clipboard(LinuxClipboardKind::Primary)
+            .text()
+            .map_err(|err| failed("read selection", err))
+    }
+
+    async fn clipboard_text(&self) -> Result<String, ActionError> {
+        self.resources
+            .clipboard
+            .lock()
+            .await
+            .get_text()
+            .map_err(|err| failed("read clipboard", err))
+    }
+
+    async fn set_clipboard_text(&self, text: String) -> Result<(), ActionError> {
+        self.resources
+            .clipboard
+            .lock()
+            .await
+            .set_text(text)
+            .map_err(|err| failed("write clipboard", err))
+    }
+
+    async fn paste(&self, text: String) -> Result<(), ActionError> {
+        paste(&self.resources, self.layout, text).await
+    }
+
+    async fn notify(&self, body: &str) -> Result<(), ActionError> {
+        Notification::new()
+            .summary("Dhe")
+            .body(body)
+            .show_async()
+            .await
+            .map_err(|err| failed("show notification", err))?;
+        Ok(())
+    }
+
+    fn retype_last_word(&self) -> Result<(), ActionError> {
+        if !self.inline {
+            return Err(ActionError::Failed(
+                "only inline actions can retype the last word".to_string(),
+            ));
         }
+        self.retype_requested.store(true, Ordering::Relaxed);
+        Ok(())
     }
 }
 
-async fn action_step(
-    listener: &mut KeyboardListener,
-    emulator: &mut KeyboardEmulator,
-    clipboard: &mut Clipboard,
-    detector: &LanguageDetector,
+/// Configuration file whose bindings are reloaded when it changes or on SIGHUP.
+/// The other sections take effect after a restart, except the sequence timeout.
+pub struct ConfigReload {
+    pub path: PathBuf,
+    pub registry: ActionRegistry,
+}
+
+pub async fn start_action_listener_loop(
+    params: Vec<ActionListenerParam>,
+    translator: CachedTranslator<FallbackTranslator>,
+    detector: SourceLanguageDetector,
+    listener_settings: ListenerSettings,
+    paste_settings: PasteSettings,
+    reload: ConfigReload,
 ) -> Result<(), anyhow::Error> {
-    if let Some(action) = listener.get_action()? {
-        match action {
-            TRANSLATE_TO_NOTIFY_ACTION => translate_to_notify_action(clipboard, detector).await,
-            TRANSLATE_TO_PASTE_ACTION => {
-                translate_to_paste_action(clipboard, emulator, detector).await
+    let ListenerSettings {
+        device_filter,
+        sequence_timeout,
+        layout_tracker,
+        layout_switch_keys,
+    } = listener_settings;
+    let layouts = layout_tracker
+        .as_ref()
+        .map(|tracker| tracker.layouts().to_vec())
+        .unwrap_or_default();
+    let mut listener = KeyboardListener::with_filter(device_filter)?;
+    register_bindings(&mut listener, params)?;
+    listener.set_sequence_timeout(sequence_timeout);
+    if let Some(tracker) = layout_tracker {
+        listener.set_layout_tracker(tracker);
+    }
+
+    let mut emulator = KeyboardEmulator::new()?;
+    emulator.set_key_delay(paste_settings.key_delay);
+    let resources = Arc::new(ActionResources {
+        translator,
+        detector,
+        emulator: Mutex::new(emulator),
+        clipboard: Mutex::new(Clipboard::new()?),
+        paste_method: paste_settings.method,
+        layouts,
+        layout_switch_keys: layout_switch_keys.clone(),
+    });
+    let mut cache_stats = resources.translator.stats();
+    let mut retyper = LastWordRetyper::new(layout_switch_keys);
+    let mut watcher = ConfigWatcher::new(&reload.path)
+        .inspect_err(|err| warn!("changes of {:?} are not watched: {err}", reload.path))
+        .ok();
+
+    loop {
+        // Reading the keys is cancelled to reload the configuration.
+        let fired = tokio::select! {
+            fired = next_action(&mut listener) => fired,
+            changed = config_changed(&mut watcher) => {
+                match changed {
+                    Ok(()) => reload_config(&mut listener, &reload),
+                    Err(err) => {
+                        error!("failed to watch the config: {err}");
+                        watcher = None;
+                    }
+                }
+                continue;
             }
-            OPEN_GUI_ACTION => open_gui(),
-            data => {
-                warn!("unregistered keyboard action {data}");
-                Ok(())
+        };
+        match fired {
+            Ok(Some(action)) => {
+                if let Err(err) =
+                    start_action(&mut listener, &resources, &mut retyper, action).await
+                {
+                    error!("action error: {err}")
+                }
             }
+            Ok(None) => {}
+            Err(err) => error!("keyboard error: {err}"),
+        }
+        if let Err(err) = retyper.step(&mut listener, &resources.emulator).await {
+            error!("retype error: {err}")
+        }
+
+        let stats = resources.translator.stats();
+        if stats != cache_stats {
+            info!(
+                "translation cache: {} hits, {} misses",
+                stats.hits, stats.misses
+            );
+            cache_stats = stats;
         }
-    } else {
-        Ok(())
     }
 }
 
-async fn translate_to_notify_action(
-    clipboard: &mut Clipboard,
-    detector: &LanguageDetector,
-) -> anyhow::Result<()> {
-    const LANGUAGE_TO_NOTIFY: Language = Language::Ru;
-    const ALTERNATIVE_LANGUAGE_TO_NOTIFY: Language = Language::En;
+/// Bind the actions in place of the bound ones. The devices needed by the new bindings
+/// are set up first, so that the old bindings are kept if it fails.
+fn register_bindings(
+    listener: &mut KeyboardListener<RegisteredAction>,
+    params: Vec<ActionListenerParam>,
+) -> Result<(), anyhow::Error> {
+    if params.iter().any(|param| param.keys.has_pointer_keys()) {
+        listener.read_pointers()?;
+    }
+    if params.iter().any(|param| param.grab) {
+        listener.grab()?;
+    }
+
+    listener.clear_actions();
+    for param in params {
+        let action = RegisteredAction {
+            name: param.name,
+            action: param.action.into(),
+        };
+        listener.register_sequence(action, &param.keys, param.trigger, param.grab);
+    }
+    Ok(())
+}
 
-    let text = clipboard
-        .get()
-        .clipboard(LinuxClipboardKind::Primary)
-        .text()?;
-    let lang = detector.recognize(&text)?;
+/// Replace the bindings with the ones of the changed configuration.
+/// If the configuration is wrong, the old bindings are kept and a notification is shown.
+fn reload_config(listener: &mut KeyboardListener<RegisteredAction>, reload: &ConfigReload) {
+    let reloaded = CliCommandsConfig::parse(Some(&reload.path))
+        .map_err(anyhow::Error::from)
+        .and_then(|config| {
+            let params = config.action_listener_params(&reload.registry)?;
+            let settings = config.listener_settings()?;
+            let count = params.len();
+            register_bindings(listener, params)?;
+            listener.set_sequence_timeout(settings.sequence_timeout);
+            Ok(count)
+        });
 
-    let (from, to) = if lang != LANGUAGE_TO_NOTIFY {
-        (lang, LANGUAGE_TO_NOTIFY)
-    } else {
-        (lang, ALTERNATIVE_LANGUAGE_TO_NOTIFY)
-    };
-    let text = translate(&text, from, to).await?;
+    match reloaded {
+        Ok(count) => info!("reloaded {count} bindings from {:?}", reload.path),
+        Err(err) => {
+            error!("config is not reloaded: {err}");
+            tokio::spawn(async move {
+                let shown = Notification::new()
+                    .summary("Dhe")
+                    .body(&format!("The config is not reloaded: {err}"))
+                    .show_async()
+                    .await;
+                if let Err(err) = shown {
+                    error!("failed to show notification: {err}");
+                }
+            });
+        }
+    }
+}
+
+/// Wait until the configuration should be reloaded, forever if it is not watched.
+async fn config_changed(watcher: &mut Option<ConfigWatcher>) -> io::Result<()> {
+    match watcher {
+        Some(watcher) => watcher.changed().await,
+        None => future::pending().await,
+    }
+}
+
+async fn next_action(
+    listener: &mut KeyboardListener<RegisteredAction>,
+) -> Result<Option<RegisteredAction>, KeyboardError> {
+    Ok(listener.get_action().await?.cloned())
+}
+
+/// Run the action fired by the last keys.
+/// Inline actions run right away, the others as separate tasks so that the listener
+/// keeps reading keys while they run.
+async fn start_action(
+    listener: &mut KeyboardListener<RegisteredAction>,
+    resources: &Arc<ActionResources>,
+    retyper: &mut LastWordRetyper,
+    action: RegisteredAction,
+) -> Result<(), anyhow::Error> {
+    let RegisteredAction { name, action } = action;
+    let layout = listener.active_layout();
+    if action.is_inline() {
+        let context = WorkerContext::new(resources.clone(), layout, true);
+        action.run(&context).await?;
+        if context.retype_requested.load(Ordering::Relaxed) {
+            retyper.request()?;
+        }
+        return Ok(());
+    }
 
-    Notification::new()
-        .summary("Dhe")
-        .body(&text)
-        .show_async()
-        .await?;
+    let context = WorkerContext::new(resources.clone(), layout, false);
+    tokio::spawn(async move {
+        if let Err(err) = action.run(&context).await {
+            error!("action {name} error: {err}")
+        }
+    });
     Ok(())
 }
 
-async fn translate_to_paste_action(
-    clipboard: &mut Clipboard,
-    emulator: &mut KeyboardEmulator,
-    detector: &LanguageDetector,
-) -> anyhow::Result<()> {
-    const LANGUAGE_TO_PASTE: Language = Language::En;
-    const ALTERNATIVE_LANGUAGE_TO_PASTE: Language = Language::Ru;
-
-    let text = clipboard
-        .get()
-        .clipboard(LinuxClipboardKind::Primary)
-        .text()?;
-    let lang = detector.recognize(&text)?;
-
-    let (from, to) = if lang != LANGUAGE_TO_PASTE {
-        (lang, LANGUAGE_TO_PASTE)
-    } else {
-        (lang, ALTERNATIVE_LANGUAGE_TO_PASTE)
-    };
-    let text = translate(&text, from, to).await?;
+/// Paste the text in place of the selection, keeping the clipboard contents.
+async fn paste(
+    resources: &ActionResources,
+    layout: Option<Language>,
+    text: String,
+) -> Result<(), ActionError> {
+    if resources.paste_method == PasteMethod::Typing {
+        return type_text(resources, layout, &text).await;
+    }
 
+    let mut clipboard = resources.clipboard.lock().await;
     let clipboard_image = clipboard.get_image().ok();
     let clipboard_text = clipboard.get_text().ok();
 
-    clipboard.set_text(text)?;
-    emulator.ctrl_v()?;
+    clipboard
+        .set_text(text)
+        .map_err(|err| failed("write clipboard", err))?;
+    resources
+        .emulator
+        .lock()
+        .await
+        .ctrl_v()
+        .map_err(ActionError::Keyboard)?;
     sleep(Duration::from_millis(100)).await;
     if let Some(clipboard_image) = clipboard_image {
-        clipboard.set_image(clipboard_image)?;
+        clipboard
+            .set_image(clipboard_image)
+            .map_err(|err| failed("restore clipboard", err))?;
     }
     if let Some(clipboard_text) = clipboard_text {
-        clipboard.set_text(clipboard_text)?;
+        clipboard
+            .set_text(clipboard_text)
+            .map_err(|err| failed("restore clipboard", err))?;
     }
 
     Ok(())
 }
 
-fn open_gui() -> anyhow::Result<()> {
-    Command::new("dhe_gui").spawn()?;
+/// Type the text in the tracked layouts, switching them by the switch keys where the current
+/// one has no keys for the characters, since the keys typed in another layout produce other
+/// characters. The active layout is restored afterwards.
+/// Without tracking, the first built-in layout is assumed to be active.
+async fn type_text(
+    resources: &ActionResources,
+    layout: Option<Language>,
+    text: &str,
+) -> Result<(), ActionError> {
+    let layouts = if resources.layouts.is_empty() {
+        vec![LAYOUTS[0].language()]
+    } else {
+        resources.layouts.clone()
+    };
+    let active = layout
+        .and_then(|layout| layouts.iter().position(|&l| l == layout))
+        .unwrap_or(0);
+    let runs = typing_runs(text, &layouts, active)?;
+    let switching = runs.iter().find(|(index, _, _)| *index != active);
+    if let Some((_, keyboard_layout, _)) = switching {
+        if resources.layout_switch_keys.is_empty() {
+            return Err(ActionError::Failed(format!(
+                "the text needs the {} layout, but layout switch keys are not configured",
+                keyboard_layout.name()
+            )));
+        }
+    }
+
+    let count = layouts.len();
+    let mut emulator = resources.emulator.lock().await;
+    let mut current = active;
+    let mut typed = Ok(());
+    for (index, keyboard_layout, run) in runs {
+        typed = switch_layout(
+            &mut emulator,
+            &resources.layout_switch_keys,
+            (index + count - current) % count,
+        );
+        if typed.is_err() {
+            break;
+        }
+        current = index;
+        typed = emulator.type_text(&run, keyboard_layout).await;
+        if typed.is_err() {
+            break;
+        }
+    }
+    // The active layout is restored even if the text is not typed.
+    switch_layout(
+        &mut emulator,
+        &resources.layout_switch_keys,
+        (active + count - current) % count,
+    )
+    .map_err(ActionError::Keyboard)?;
+    typed.map_err(ActionError::Keyboard)
+}
+
+fn switch_layout(
+    emulator: &mut KeyboardEmulator,
+    switch_keys: &[Key],
+    switches: usize,
+) -> Result<(), KeyboardError> {
+    for _ in 0..switches {
+        emulator.press_combination(switch_keys)?;
+    }
     Ok(())
 }
+
+/// Runs of the text with the index of the layout to type each of them in and its keys.
+/// A character is typed in the current layout if it has a key there, otherwise in the next
+/// layout that has one. Characters without keys are typed by their hex codes in a layout
+/// that has keys for the digits.
+fn typing_runs(
+    text: &str,
+    layouts: &[Language],
+    active: usize,
+) -> Result<Vec<(usize, &'static KeyboardLayout, String)>, ActionError> {
+    let keyboard_layouts: Vec<_> = layouts
+        .iter()
+        .map(|&language| KeyboardLayout::for_language(language))
+        .collect();
+    let mut runs: Vec<(usize, &'static KeyboardLayout, String)> = vec![];
+    let mut current = active;
+    for c in text.chars() {
+        // Layouts in the switching order, starting from the current one.
+        let next_layouts = || {
+            (0..layouts.len())
+                .map(|shift| (current + shift) % layouts.len())
+                .filter_map(|index| Some((index, keyboard_layouts[index]?)))
+        };
+        let (index, keyboard_layout) = next_layouts()
+            .find(|(_, keyboard_layout)| keyboard_layout.key_of(c).is_some())
+            .or_else(|| {
+                next_layouts().find(|(_, keyboard_layout)| keyboard_layout.code_keys(c).is_some())
+            })
+            .ok_or_else(|| ActionError::Failed(format!("no layout can type {c:?}")))?;
+        match runs.last_mut() {
+            Some((last, _, run)) if *last == index => run.push(c),
+            _ => runs.push((index, keyboard_layout, c.to_string())),
+        }
+        current = index;
+    }
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typing_layouts() {
+        let layouts = [Language::En, Language::Ru];
+        let runs = |text, active| {
+            typing_runs(text, &layouts, active)
+                .unwrap()
+                .into_iter()
+                .map(|(index, _, run)| (index, run))
+                .collect::<Vec<_>>()
+        };
+        let run = |index, text: &str| (index, text.to_string());
+
+        // An English translation is typed in the English layout while the Russian one is active.
+        assert_eq!(vec![run(0, "Hello, world!")], runs("Hello, world!", 1));
+        assert_eq!(vec![run(1, "Привет, мир!")], runs("Привет, мир!", 0));
+        // Punctuation is typed in the active layout.
+        assert_eq!(vec![run(1, "1, 2.")], runs("1, 2.", 1));
+        assert_eq!(
+            vec![run(1, "Привет, "), run(0, "world")],
+            runs("Привет, world", 1)
+        );
+        // The hex codes of the quotes are typed in the layout that has keys for a-f.
+        assert_eq!(
+            vec![run(0, "«"), run(1, "Привет"), run(0, "»")],
+            runs("«Привет»", 1)
+        );
+        assert!(typing_runs("«Привет»", &[Language::Ru], 0).is_err());
+    }
+}
@@ -0,0 +1,429 @@
+use std::process::Command;
+
+use async_trait::async_trait;
+use dhe_sdk::{
+    action::{Action, ActionContext, ActionError, ActionRegistry},
+    language::Language,
+    translate::Translation,
+};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::shell_command::{ShellCommand, ShellInput, ShellOutput};
+
+pub const TRANSLATE_TO_NOTIFY_ACTION: &str = "translate-to-notify";
+pub const TRANSLATE_TO_PASTE_ACTION: &str = "translate-to-paste";
+pub const OPEN_GUI_ACTION: &str = "open-gui";
+pub const FIX_LAYOUT_ACTION: &str = "fix-layout";
+pub const RETYPE_LAST_WORD_ACTION: &str = "retype-last-word";
+pub const RUN_COMMAND_ACTION: &str = "run-command";
+
+/// Registry of the actions dhe has out of the box.
+pub fn builtin_actions() -> ActionRegistry {
+    let mut registry = ActionRegistry::new();
+    registry.register::<TranslateToNotify>(TRANSLATE_TO_NOTIFY_ACTION);
+    registry.register::<TranslateToPaste>(TRANSLATE_TO_PASTE_ACTION);
+    registry.register::<OpenGui>(OPEN_GUI_ACTION);
+    registry.register::<FixLayout>(FIX_LAYOUT_ACTION);
+    registry.register::<RetypeLastWord>(RETYPE_LAST_WORD_ACTION);
+    registry.register::<ShellCommand>(RUN_COMMAND_ACTION);
+    registry
+}
+
+/// Options of the translate actions.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TranslateOptions {
+    /// Language to translate into.
+    target_language: Option<Language>,
+    /// Language to translate into when the text is already in the target language.
+    fallback_language: Option<Language>,
+}
+
+impl TranslateOptions {
+    /// The configured languages or the defaults of the action.
+    /// If only the target language is configured, the fallback is the default target of the action.
+    fn languages(
+        &self,
+        default_target: Language,
+        default_fallback: Language,
+    ) -> TranslateLanguages {
+        let target = self.target_language.unwrap_or(default_target);
+        let fallback = self
+            .fallback_language
+            .unwrap_or(if target != default_target {
+                default_target
+            } else {
+                default_fallback
+            });
+        TranslateLanguages { target, fallback }
+    }
+}
+
+/// Languages to translate into.
+#[derive(Debug, Copy, Clone)]
+struct TranslateLanguages {
+    target: Language,
+    /// Language to translate into when the text is already in the target language.
+    fallback: Language,
+}
+
+impl TranslateLanguages {
+    fn validate(&self) -> Result<(), String> {
+        if self.target == self.fallback {
+            return Err("target and fallback languages are the same".to_string());
+        }
+        Ok(())
+    }
+
+    /// Translation direction for the text in the given language.
+    fn direction(&self, lang: Language) -> (Language, Language) {
+        if lang != self.target {
+            (lang, self.target)
+        } else {
+            (lang, self.fallback)
+        }
+    }
+}
+
+/// Translation of the selected text into the target language of the action.
+async fn translate_selection(
+    ctx: &dyn ActionContext,
+    languages: TranslateLanguages,
+) -> Result<Translation, ActionError> {
+    let text = ctx.selected_text().await?;
+    let lang = ctx.detect_language(&text)?;
+
+    let (from, to) = languages.direction(lang);
+    ctx.translate(&text, from, to).await
+}
+
+/// Show the translation of the selected text in a notification, into Russian by default.
+#[derive(Debug, Deserialize)]
+#[serde(transparent)]
+pub struct TranslateToNotify(TranslateOptions);
+
+impl TranslateToNotify {
+    fn languages(&self) -> TranslateLanguages {
+        self.0.languages(Language::Ru, Language::En)
+    }
+}
+
+#[async_trait]
+impl Action for TranslateToNotify {
+    async fn run(&self, ctx: &dyn ActionContext) -> Result<(), ActionError> {
+        let translation = translate_selection(ctx, self.languages()).await?;
+        ctx.notify(&notification_body(&translation)).await
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.languages().validate()
+    }
+}
+
+/// Replace the selected text with its translation, into English by default.
+#[derive(Debug, Deserialize)]
+#[serde(transparent)]
+pub struct TranslateToPaste(TranslateOptions);
+
+impl TranslateToPaste {
+    fn languages(&self) -> TranslateLanguages {
+        self.0.languages(Language::En, Language::Ru)
+    }
+}
+
+#[async_trait]
+impl Action for TranslateToPaste {
+    async fn run(&self, ctx: &dyn ActionContext) -> Result<(), ActionError> {
+        let translation = translate_selection(ctx, self.languages()).await?;
+        ctx.paste(translation.text).await
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.languages().validate()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OpenGui {}
+
+#[async_trait]
+impl Action for OpenGui {
+    async fn run(&self, _: &dyn ActionContext) -> Result<(), ActionError> {
+        Command::new("dhe_gui")
+            .spawn()
+            .map_err(|err| ActionError::Failed(format!("open gui: {err}")))?;
+        Ok(())
+    }
+}
+
+/// Replace the selected text typed in a wrong layout with the text of the right one.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixLayout {}
+
+#[async_trait]
+impl Action for FixLayout {
+    async fn run(&self, ctx: &dyn ActionContext) -> Result<(), ActionError> {
+        let text = ctx.selected_text().await?;
+        let Some(text) = ctx.fix_layout(&text) else {
+            debug!("nothing to fix in the layout of the text");
+            return Ok(());
+        };
+        ctx.paste(text).await
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetypeLastWord {}
+
+#[async_trait]
+impl Action for RetypeLastWord {
+    async fn run(&self, ctx: &dyn ActionContext) -> Result<(), ActionError> {
+        ctx.retype_last_word()
+    }
+
+    fn is_inline(&self) -> bool {
+        true
+    }
+}
+
+#[async_trait]
+impl Action for ShellCommand {
+    async fn run(&self, ctx: &dyn ActionContext) -> Result<(), ActionError> {
+        let text = match self.input {
+            ShellInput::Primary => Some(ctx.selected_text().await?),
+            ShellInput::Clipboard => Some(ctx.clipboard_text().await?),
+            ShellInput::None => None,
+        };
+        let output = self
+            .execute(text)
+            .await
+            .map_err(|err| ActionError::Failed(err.to_string()))?;
+
+        match self.output {
+            ShellOutput::Paste => ctx.paste(output).await,
+            ShellOutput::Notify => ctx.notify(&output).await,
+            ShellOutput::Clipboard => ctx.set_clipboard_text(output).await,
+            ShellOutput::None => Ok(()),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.command.is_empty() {
+            return Err("command is not specified".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Notification text: the primary translation with the part of speech, alternatives and examples.
+fn notification_body(translation: &Translation) -> String {
+    let mut body = translation.text.clone();
+    if let Some(pos) = &translation.part_of_speech {
+        body.push_str(&format!(" ({pos})"));
+    }
+    if !translation.alternatives.is_empty() {
+        body.push_str(&format!("\n{}", translation.alternatives.join(", ")));
+    }
+    for example in &translation.examples {
+        body.push_str(&format!("\n{example}"));
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Context with a fixed selection and language that records what the actions do.
+    #[derive(Default)]
+    struct FakeContext {
+        selection: String,
+        language: Option<Language>,
+        clipboard: Mutex<String>,
+        pasted: Mutex<Vec<String>>,
+        notified: Mutex<Vec<String>>,
+    }
+
+    impl FakeContext {
+        fn new(selection: &str, language: Language) -> Self {
+            Self {
+                selection: selection.to_string(),
+                language: Some(language),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ActionContext for FakeContext {
+        fn layout(&self) -> Option<Language> {
+            None
+        }
+
+        fn detect_language(&self, _: &str) -> Result<Language, ActionError> {
+            self.language
+                .ok_or_else(|| ActionError::Failed("unknown language".to_string()))
+        }
+
+        fn fix_layout(&self, text: &str) -> Option<String> {
+            dhe_sdk::keyboard::fix_layout(text)
+        }
+
+        async fn translate(
+            &self,
+            text: &str,
+            from: Language,
+            to: Language,
+        ) -> Result<Translation, ActionError> {
+            Ok(Translation::new(format!("{text} {from}->{to}"), "fake"))
+        }
+
+        async fn selected_text(&self) -> Result<String, ActionError> {
+            Ok(self.selection.clone())
+        }
+
+        async fn clipboard_text(&self) -> Result<String, ActionError> {
+            Ok(self.clipboard.lock().unwrap().clone())
+        }
+
+        async fn set_clipboard_text(&self, text: String) -> Result<(), ActionError> {
+            *self.clipboard.lock().unwrap() = text;
+            Ok(())
+        }
+
+        async fn paste(&self, text: String) -> Result<(), ActionError> {
+            self.pasted.lock().unwrap().push(text);
+            Ok(())
+        }
+
+        async fn notify(&self, body: &str) -> Result<(), ActionError> {
+            self.notified.lock().unwrap().push(body.to_string());
+            Ok(())
+        }
+
+        fn retype_last_word(&self) -> Result<(), ActionError> {
+            Ok(())
+        }
+    }
+
+    fn build(name: &str, options: &str) -> Result<Box<dyn Action>, ActionError> {
+        builtin_actions().build(name, toml::from_str(options).unwrap())
+    }
+
+    #[tokio::test]
+    async fn translate_directions() {
+        let action = build(TRANSLATE_TO_PASTE_ACTION, "").unwrap();
+
+        let ctx = FakeContext::new("привет", Language::Ru);
+        action.run(&ctx).await.unwrap();
+        let ctx_en = FakeContext::new("hello", Language::En);
+        action.run(&ctx_en).await.unwrap();
+        assert_eq!(vec!["привет ru->en"], *ctx.pasted.lock().unwrap());
+        assert_eq!(vec!["hello en->ru"], *ctx_en.pasted.lock().unwrap());
+
+        let action = build(TRANSLATE_TO_NOTIFY_ACTION, "target-language = \"en\"").unwrap();
+        action.run(&ctx).await.unwrap();
+        assert_eq!(vec!["привет ru->en"], *ctx.notified.lock().unwrap());
+    }
+
+    #[test]
+    fn wrong_options() {
+        assert!(matches!(
+            build(
+                TRANSLATE_TO_PASTE_ACTION,
+                "target-language = \"ru\"\nfallback-language = \"ru\""
+            ),
+            Err(ActionError::WrongOptions(..))
+        ));
+        assert!(matches!(
+            build(FIX_LAYOUT_ACTION, "target-language = \"ru\""),
+            Err(ActionError::WrongOptions(..))
+        ));
+        assert!(matches!(
+            build(RUN_COMMAND_ACTION, "command = []"),
+            Err(ActionError::WrongOptions(..))
+        ));
+        assert!(matches!(
+            build("translate", ""),
+            Err(ActionError::UnknownAction(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn fix_layout() {
+        let action = build(FIX_LAYOUT_ACTION, "").unwrap();
+        let ctx = FakeContext::new("ghbdtn", Language::En);
+        action.run(&ctx).await.unwrap();
+        let ctx_wrong_ru = FakeContext::new("руддщ", Language::Ru);
+        action.run(&ctx_wrong_ru).await.unwrap();
+        assert_eq!(vec!["привет"], *ctx.pasted.lock().unwrap());
+        assert_eq!(vec!["hello"], *ctx_wrong_ru.pasted.lock().unwrap());
+
+        // Correctly typed text is kept as it is.
+        for (text, language) in [("привет", Language::Ru), ("hello", Language::En)] {
+            let ctx_fixed = FakeContext::new(text, language);
+            action.run(&ctx_fixed).await.unwrap();
+            assert!(ctx_fixed.pasted.lock().unwrap().is_empty(), "{text}");
+        }
+    }
+
+    #[tokio::test]
+    async fn run_command() {
+        let ctx = FakeContext::new("hello", Language::En);
+
+        let action = build(RUN_COMMAND_ACTION, r#"command = ["tr", "a-z", "A-Z"]"#).unwrap();
+        action.run(&ctx).await.unwrap();
+        assert_eq!(vec!["HELLO"], *ctx.pasted.lock().unwrap());
+
+        let action = build(
+            RUN_COMMAND_ACTION,
+            r#"
+            command = ["sh", "-c", "echo \"$DHE_TEXT, world\""]
+            input-via = "env"
+            output = "clipboard"
+            "#,
+        )
+        .unwrap();
+        action.run(&ctx).await.unwrap();
+        assert_eq!("hello, world", *ctx.clipboard.lock().unwrap());
+
+        let action = build(
+            RUN_COMMAND_ACTION,
+            r#"
+            command = ["sh", "-c", "exit 3"]
+            input = "none"
+            "#,
+        )
+        .unwrap();
+        assert!(action.run(&ctx).await.is_err());
+
+        let action = build(
+            RUN_COMMAND_ACTION,
+            r#"
+            command = ["sleep", "5"]
+            input = "none"
+            timeout-ms = 100
+            "#,
+        )
+        .unwrap();
+        let err = action.run(&ctx).await.err().unwrap();
+        assert!(err.to_string().contains("didn't finish"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn run_command_large_input() {
+        // Larger than the pipe buffers, the filter writes its output before it reads all the input.
+        let text = "word ".repeat(200_000);
+        let ctx = FakeContext::new(&text, Language::En);
+
+        let action = build(RUN_COMMAND_ACTION, r#"command = ["cat"]"#).unwrap();
+        action.run(&ctx).await.unwrap();
+        assert_eq!(vec![text], *ctx.pasted.lock().unwrap());
+    }
+}
@@ -1,21 +1,40 @@
 use std::{
+    collections::{BTreeMap, HashSet},
     fs,
     path::{Path, PathBuf},
-    process::Command,
     str::FromStr,
-    thread::sleep,
     time::Duration,
 };
 
-use dhe_sdk::keyboard::Key;
-use serde::Deserialize;
+use dhe_sdk::{
+    action::ActionRegistry,
+    keyboard::{
+        DeviceFilter, DeviceRule, Key, KeySequence, LayoutSwitch, LayoutTracker, Led, Trigger,
+        DEFAULT_SEQUENCE_TIMEOUT,
+    },
+    language::{Language, LanguageDetector},
+    translate::{
+        CacheConfig, CachedTranslator, DeeplTranslator, DictionaryTranslator, FallbackTranslator,
+        GoogleTranslator, LibreTranslator, Translator, Url,
+    },
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tracing::error;
+use toml::Spanned;
+use tracing::warn;
 
-use crate::action_listener::ActionListenerParam;
+use crate::{
+    action_listener::{
+        ActionListenerParam, ListenerSettings, PasteMethod, PasteSettings, SourceLanguageDetector,
+    },
+    startup::{RestartBackoff, RestartPolicy, StartupCommand},
+};
 
 /// Default command file name in $HOME directory
 const DEFAULT_CLI_COMMAND_FILE_NAME: &str = "dhe_commands.toml";
+/// Version of the configuration schema, the files without a version have the old format.
+pub const CONFIG_VERSION: i64 = 2;
 
 #[derive(Error, Debug)]
 pub enum CliCommandError {
@@ -23,60 +42,195 @@ pub enum CliCommandError {
     CommandsFileNotFound,
     #[error("failed to read cli commands configuration file at {0} path")]
     ReadCommands(PathBuf),
-    #[error("wrong cli commands configuration file format at {0} path")]
-    WrongCommandsFormat(PathBuf),
-    #[error("failed to execute/validate command {0} with args {1:?}; error: {2}")]
-    ExecuteCommand(String, Vec<String>, String),
+    #[error("wrong cli commands configuration file format at {0} path: {1}")]
+    WrongCommandsFormat(PathBuf, String),
+    #[error("configuration file at {0} path is already of version {CONFIG_VERSION}")]
+    AlreadyMigrated(PathBuf),
+    #[error("wrong binding {0}: {1}")]
+    WrongBinding(String, String),
+    #[error("wrong startup command {0:?}: {1}")]
+    WrongStartup(Vec<String>, String),
+    #[error("failed to set up translator: {0}")]
+    SetupTranslator(String),
+    #[error("failed to set up language detector: {0}")]
+    SetupDetector(String),
+    #[error("failed to set up keyboard layout tracking: {0}")]
+    SetupLayout(String),
+    #[error("failed to set up device filter: {0}")]
+    SetupDevices(String),
 }
 
 #[derive(Deserialize)]
 pub struct CliCommandsConfig {
-    commands: Vec<CliCommand>,
+    /// Key bindings of the actions.
+    #[serde(default)]
+    binding: Vec<Spanned<BindingConfig>>,
+    /// Commands started with the worker.
+    #[serde(default)]
+    startup: Vec<StartupConfig>,
+    /// Translation backends in the order in which they are tried.
+    #[serde(default)]
+    translators: Vec<TranslatorConfig>,
+    #[serde(default)]
+    cache: TranslationCacheConfig,
+    #[serde(default)]
+    detection: DetectionConfig,
+    #[serde(default)]
+    layout: LayoutConfig,
+    #[serde(default)]
+    paste: PasteConfig,
+    #[serde(default)]
+    keyboard: KeyboardConfig,
+    #[serde(default)]
+    devices: DevicesConfig,
+    /// Text of the file to locate the bindings, `None` for a migrated configuration.
+    #[serde(skip)]
+    source: Option<String>,
 }
 
 impl CliCommandsConfig {
     pub fn parse<P: AsRef<Path>>(commands_file_path: Option<P>) -> Result<Self, CliCommandError> {
         use CliCommandError::*;
 
-        let path = commands_file_path
+        let path = Self::path(commands_file_path)?;
+        let commands_data = fs::read_to_string(&path).map_err(|_| ReadCommands(path.clone()))?;
+        Self::from_toml(&commands_data).map_err(|err| WrongCommandsFormat(path, err))
+    }
+
+    /// Path of the configuration file, `dhe_commands.toml` in the home directory by default.
+    pub fn path<P: AsRef<Path>>(commands_file_path: Option<P>) -> Result<PathBuf, CliCommandError> {
+        commands_file_path
             .map(|p| p.as_ref().to_path_buf())
             .or_else(Self::default_cli_command_file_name)
-            .ok_or(CommandsFileNotFound)?;
+            .ok_or(CliCommandError::CommandsFileNotFound)
+    }
 
-        let commands_data = fs::read_to_string(&path).map_err(|_| ReadCommands(path.clone()))?;
-        toml::from_str(&commands_data).map_err(|_| WrongCommandsFormat(path))
+    /// Configuration of the current version, a configuration of the old format is migrated first.
+    fn from_toml(data: &str) -> Result<Self, String> {
+        let table: toml::Table = toml::from_str(data).map_err(|err| toml_error(data, &err))?;
+        match table.get("version") {
+            None => {
+                warn!("the configuration has the old format, convert it with `dhe_worker migrate-config`");
+                let migrated = migrate_config(data)?;
+                toml::from_str(&migrated).map_err(|err| err.message().to_string())
+            }
+            Some(toml::Value::Integer(CONFIG_VERSION)) => {
+                let mut config: Self =
+                    toml::from_str(data).map_err(|err| toml_error(data, &err))?;
+                config.source = Some(data.to_string());
+                Ok(config)
+            }
+            Some(version) => Err(format!(
+                "unsupported configuration version {version}, the latest one is {CONFIG_VERSION}"
+            )),
+        }
     }
 
-    pub fn execute_bash_starter_commands(&self) -> Result<(), CliCommandError> {
-        for command in self.by_handler(CliCommandHandler::BashStarter) {
-            sleep(Duration::from_millis(300));
-            let CliCommand { name, args, .. } = command;
+    /// Configuration file converted from the old format to the current one.
+    pub fn migrate_file(path: &Path) -> Result<String, CliCommandError> {
+        use CliCommandError::*;
 
-            Command::new(name).args(args).spawn().map_err(|err| {
-                error!("cli command {name} {args:?} error: {err}");
-                CliCommandError::ExecuteCommand(name.clone(), args.clone(), err.to_string())
-            })?;
+        let data = fs::read_to_string(path).map_err(|_| ReadCommands(path.to_path_buf()))?;
+        let table: toml::Table = toml::from_str(&data)
+            .map_err(|err| WrongCommandsFormat(path.to_path_buf(), toml_error(&data, &err)))?;
+        if table.contains_key("version") {
+            return Err(AlreadyMigrated(path.to_path_buf()));
         }
+        migrate_config(&data).map_err(|err| WrongCommandsFormat(path.to_path_buf(), err))
+    }
 
-        Ok(())
+    /// Startup commands, the dependencies of each one are checked to be listed before it.
+    pub fn startup_commands(&self) -> Result<Vec<StartupCommand>, CliCommandError> {
+        let mut names = HashSet::new();
+        self.startup
+            .iter()
+            .map(|startup| {
+                let wrong =
+                    |err: String| CliCommandError::WrongStartup(startup.command.clone(), err);
+                let Some((program, args)) = startup.command.split_first() else {
+                    return Err(wrong("empty command".to_string()));
+                };
+                let name = match &startup.name {
+                    Some(name) => name.clone(),
+                    None => Path::new(program)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| program.clone()),
+                };
+                if let Some(dependency) = startup.after.iter().find(|dep| !names.contains(*dep)) {
+                    return Err(wrong(format!(
+                        "{dependency} is not a startup command listed before this one"
+                    )));
+                }
+                if !names.insert(name.clone()) {
+                    return Err(wrong(format!(
+                        "name {name} is already used, set another one with `name`"
+                    )));
+                }
+
+                let mut backoff = RestartBackoff::default();
+                if let Some(delay_ms) = startup.restart_delay_ms {
+                    backoff.delay = Duration::from_millis(delay_ms);
+                }
+                if let Some(max_delay_ms) = startup.max_restart_delay_ms {
+                    backoff.max_delay = Duration::from_millis(max_delay_ms);
+                }
+                if backoff.max_delay < backoff.delay {
+                    return Err(wrong(
+                        "max-restart-delay-ms is less than restart-delay-ms".to_string(),
+                    ));
+                }
+                let log = startup.log.clone().or_else(|| {
+                    dirs::state_dir().map(|dir| dir.join("dhe").join(format!("{name}.log")))
+                });
+                Ok(StartupCommand {
+                    name,
+                    program: program.clone(),
+                    args: args.to_vec(),
+                    cwd: startup.cwd.clone(),
+                    env: startup.env.clone(),
+                    delay: Duration::from_millis(startup.delay_ms),
+                    after: startup.after.clone(),
+                    ready_delay: Duration::from_millis(startup.ready_delay_ms),
+                    restart: startup.restart,
+                    backoff,
+                    log,
+                })
+            })
+            .collect()
     }
 
+    /// Bindings of the actions, each action is built from the options of its binding.
     pub fn action_listener_params(
         &self,
-    ) -> Result<Vec<ActionListenerParam<&str>>, CliCommandError> {
-        self.by_handler(CliCommandHandler::ActionListener)
-            .map(|c| {
+        registry: &ActionRegistry,
+    ) -> Result<Vec<ActionListenerParam>, CliCommandError> {
+        self.binding
+            .iter()
+            .map(|spanned| {
+                let binding = spanned.get_ref();
                 let err_gen = |err: String| {
-                    CliCommandError::ExecuteCommand(c.name.clone(), c.args.clone(), err)
+                    let name = match &self.source {
+                        Some(source) => {
+                            let (line, _) = position(source, spanned.span().start);
+                            format!("{:?} at line {line}", binding.action)
+                        }
+                        None => format!("{:?}", binding.action),
+                    };
+                    CliCommandError::WrongBinding(name, err)
                 };
 
+                let keys =
+                    KeySequence::from_str(&binding.keys).map_err(|err| err_gen(err.to_string()))?;
+                let action = registry
+                    .build(&binding.action, binding.options.clone())
+                    .map_err(|err| err_gen(err.to_string()))?;
                 let param = ActionListenerParam {
-                    name: c.name.as_str(),
-                    keys: c
-                        .args
-                        .iter()
-                        .map(|a| Key::from_str(a).map_err(|err| err_gen(err.to_string())))
-                        .collect::<Result<Vec<_>, _>>()?,
+                    name: binding.action.clone(),
+                    keys,
+                    trigger: binding.trigger(),
+                    grab: binding.grab,
+                    action,
                 };
                 param.validate().map_err(|err| err_gen(err.to_string()))?;
                 Ok(param)
@@ -84,8 +238,137 @@ impl CliCommandsConfig {
             .collect()
     }
 
-    fn by_handler(&self, handler: CliCommandHandler) -> impl Iterator<Item = &CliCommand> {
-        self.commands.iter().filter(move |&c| c.handler == handler)
+    /// Cached translator that falls back through the configured backends (google translator by default).
+    pub fn translator(&self) -> Result<CachedTranslator<FallbackTranslator>, CliCommandError> {
+        let translator = if self.translators.is_empty() {
+            FallbackTranslator::new(vec![Box::new(GoogleTranslator::new())])
+        } else {
+            // Offline dictionaries go first so that the network is only used when they have no entry.
+            let (offline, online): (Vec<_>, Vec<_>) = self
+                .translators
+                .iter()
+                .partition(|config| matches!(config, TranslatorConfig::Dictionary { .. }));
+            let backends = offline
+                .into_iter()
+                .chain(online)
+                .map(TranslatorConfig::build)
+                .collect::<Result<Vec<_>, _>>()?;
+            FallbackTranslator::new(backends)
+        };
+
+        CachedTranslator::new(translator, self.cache.to_cache_config())
+            .map_err(|err| CliCommandError::SetupTranslator(err.to_string()))
+    }
+
+    pub fn source_language_detector(&self) -> Result<SourceLanguageDetector, CliCommandError> {
+        let DetectionConfig {
+            min_confidence,
+            min_relative_distance,
+            preferred_language,
+        } = self.detection;
+        let detector = LanguageDetector::with_thresholds(min_confidence, min_relative_distance)
+            .map_err(|err| CliCommandError::SetupDetector(err.to_string()))?;
+        Ok(SourceLanguageDetector::new(
+            detector,
+            preferred_language,
+            self.layout.prior_weight,
+        ))
+    }
+
+    /// Settings of the keyboard listener: the devices, the sequence timeout and the layout tracking.
+    pub fn listener_settings(&self) -> Result<ListenerSettings, CliCommandError> {
+        Ok(ListenerSettings {
+            device_filter: self.device_filter()?,
+            sequence_timeout: self.sequence_timeout(),
+            layout_tracker: self.layout_tracker()?,
+            layout_switch_keys: self.layout_switch_keys()?,
+        })
+    }
+
+    /// Maximum time between the steps of key sequences.
+    fn sequence_timeout(&self) -> Duration {
+        self.keyboard
+            .sequence_timeout_ms
+            .map_or(DEFAULT_SEQUENCE_TIMEOUT, Duration::from_millis)
+    }
+
+    /// Keyboards to listen to by the include and exclude rules.
+    pub fn device_filter(&self) -> Result<DeviceFilter, CliCommandError> {
+        let rules = |rules: &[DeviceRuleConfig]| {
+            rules
+                .iter()
+                .map(DeviceRuleConfig::rule)
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(DeviceFilter {
+            include: rules(&self.devices.include)?,
+            exclude: rules(&self.devices.exclude)?,
+        })
+    }
+
+    pub fn paste_settings(&self) -> PasteSettings {
+        PasteSettings {
+            method: self.paste.method,
+            key_delay: Duration::from_millis(self.paste.key_delay_ms),
+        }
+    }
+
+    /// Keys emulated to switch the layout: the configured ones or the switch keys of the tracked layouts.
+    fn layout_switch_keys(&self) -> Result<Vec<Key>, CliCommandError> {
+        let LayoutConfig {
+            switch_keys,
+            emulated_switch_keys,
+            ..
+        } = &self.layout;
+        let keys = if emulated_switch_keys.is_empty() {
+            switch_keys
+        } else {
+            emulated_switch_keys
+        };
+        keys.iter()
+            .map(|key| {
+                Key::from_str(key).map_err(|err| CliCommandError::SetupLayout(err.to_string()))
+            })
+            .collect()
+    }
+
+    /// Tracker of the active keyboard layout, `None` if the layouts are not configured.
+    fn layout_tracker(&self) -> Result<Option<LayoutTracker>, CliCommandError> {
+        use CliCommandError::SetupLayout;
+
+        let LayoutConfig {
+            layouts,
+            switch_keys,
+            switch_led,
+            prior_weight,
+            ..
+        } = &self.layout;
+        if layouts.is_empty() {
+            return Ok(None);
+        }
+        if !(0.0..=1.0).contains(prior_weight) {
+            return Err(SetupLayout(format!(
+                "prior weight {prior_weight} is out of range"
+            )));
+        }
+
+        let switch = match (switch_keys.is_empty(), switch_led) {
+            (true, Some(led)) => {
+                LayoutSwitch::Led(Led::from_str(led).map_err(|err| SetupLayout(err.to_string()))?)
+            }
+            (false, None) => LayoutSwitch::Keys(
+                switch_keys
+                    .iter()
+                    .map(|key| Key::from_str(key).map_err(|err| SetupLayout(err.to_string())))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            _ => {
+                return Err(SetupLayout(
+                    "exactly one of switch-keys and switch-led must be specified".to_string(),
+                ))
+            }
+        };
+        Ok(Some(LayoutTracker::new(layouts.clone(), switch)))
     }
 
     fn default_cli_command_file_name() -> Option<PathBuf> {
@@ -96,17 +379,556 @@ impl CliCommandsConfig {
     }
 }
 
+/// Key binding of an action.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BindingConfig {
+    /// Key sequence like "LCtrl+Space, T, N".
+    keys: String,
+    /// Name of the action.
+    action: String,
+    /// When the action fires after its keys are pressed.
+    #[serde(default, skip_serializing_if = "is_default")]
+    trigger: TriggerConfig,
+    /// Duration of the long press or the maximum interval of the double tap in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trigger_ms: Option<u64>,
+    /// Swallow the keys of the action so that the focused application doesn't receive them.
+    /// The keyboards are grabbed exclusively if any action sets it.
+    #[serde(default, skip_serializing_if = "is_default")]
+    grab: bool,
+    /// Options of the action like the target language of translate actions.
+    #[serde(flatten)]
+    options: toml::Table,
+}
+
+impl BindingConfig {
+    fn trigger(&self) -> Trigger {
+        let duration = |default| self.trigger_ms.map_or(default, Duration::from_millis);
+        match self.trigger {
+            TriggerConfig::Press => Trigger::Press,
+            TriggerConfig::Release => Trigger::Release,
+            TriggerConfig::LongPress => Trigger::LongPress(duration(Trigger::DEFAULT_LONG_PRESS)),
+            TriggerConfig::DoubleTap => Trigger::DoubleTap(duration(Trigger::DEFAULT_DOUBLE_TAP)),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TriggerConfig {
+    #[default]
+    Press,
+    Release,
+    LongPress,
+    DoubleTap,
+}
+
+/// Command started with the worker when it is launched with `--init`.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct StartupConfig {
+    /// Program and its arguments.
+    command: Vec<String>,
+    /// Working directory, the one of the worker by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cwd: Option<PathBuf>,
+    /// Environment variables added to the ones of the worker.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+    /// Name used by `after` and `dhe_worker status`, the program file name by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// Commands listed before this one that have to be ready before it is started.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    after: Vec<String>,
+    /// Delay before the command is started after the previous one in milliseconds.
+    #[serde(default, skip_serializing_if = "is_default")]
+    delay_ms: u64,
+    /// Time after the start when the running command is considered ready in milliseconds.
+    #[serde(default, skip_serializing_if = "is_default")]
+    ready_delay_ms: u64,
+    #[serde(default, skip_serializing_if = "is_default")]
+    restart: RestartPolicy,
+    /// Delay before the first restart in milliseconds, doubled after each restart.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    restart_delay_ms: Option<u64>,
+    /// Maximum delay before a restart in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_restart_delay_ms: Option<u64>,
+    /// File for stdout and stderr of the command, `<name>.log` in the XDG state directory by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    log: Option<PathBuf>,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+#[derive(Deserialize)]
+#[serde(
+    tag = "backend",
+    rename_all = "kebab-case",
+    rename_all_fields = "kebab-case"
+)]
+pub enum TranslatorConfig {
+    Google {
+        url: Option<String>,
+    },
+    LibreTranslate {
+        url: String,
+        api_key: Option<String>,
+    },
+    Deepl {
+        url: Option<String>,
+        auth_key: String,
+    },
+    Dictionary {
+        path: PathBuf,
+        from: Language,
+        to: Language,
+    },
+}
+
+impl TranslatorConfig {
+    fn build(&self) -> Result<Box<dyn Translator>, CliCommandError> {
+        use TranslatorConfig::*;
+        let parse_url = |url: &str| {
+            Url::parse(url).map_err(|err| CliCommandError::SetupTranslator(format!("{url}: {err}")))
+        };
+
+        let translator: Box<dyn Translator> = match self {
+            Google { url: None } => Box::new(GoogleTranslator::new()),
+            Google { url: Some(url) } => Box::new(GoogleTranslator::with_url(parse_url(url)?)),
+            LibreTranslate { url, api_key } => {
+                Box::new(LibreTranslator::new(parse_url(url)?, api_key.clone()))
+            }
+            Deepl {
+                url: None,
+                auth_key,
+            } => Box::new(DeeplTranslator::new(auth_key)),
+            Deepl {
+                url: Some(url),
+                auth_key,
+            } => Box::new(DeeplTranslator::with_url(parse_url(url)?, auth_key)),
+            Dictionary { path, from, to } => Box::new(
+                DictionaryTranslator::load(path, *from, *to)
+                    .map_err(|err| CliCommandError::SetupTranslator(err.to_string()))?,
+            ),
+        };
+        Ok(translator)
+    }
+}
+
 #[derive(Deserialize)]
-pub struct CliCommand {
-    handler: CliCommandHandler,
+#[serde(default, rename_all = "kebab-case")]
+pub struct TranslationCacheConfig {
+    capacity: usize,
+    ttl_hours: u64,
+    /// Whether to keep the cache on disk between launches.
+    persistent: bool,
+    /// Path to the cache storage, the XDG data directory by default.
+    path: Option<PathBuf>,
+}
+
+impl TranslationCacheConfig {
+    fn to_cache_config(&self) -> CacheConfig {
+        CacheConfig {
+            capacity: self.capacity,
+            ttl: Duration::from_secs(self.ttl_hours * 60 * 60),
+            path: if self.persistent {
+                self.path.clone().or_else(CacheConfig::default_path)
+            } else {
+                None
+            },
+        }
+    }
+}
+
+impl Default for TranslationCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1000,
+            ttl_hours: 30 * 24,
+            persistent: true,
+            path: None,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DetectionConfig {
+    /// Minimum confidence (from 0 to 1) of the recognized language.
+    min_confidence: f64,
+    /// Minimum distance (from 0 to 0.99) between the confidences of the two most likely languages.
+    min_relative_distance: f64,
+    /// Source language used when the language of the text is not recognized.
+    preferred_language: Option<Language>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LayoutConfig {
+    /// Languages of the keyboard layouts in the XKB group order.
+    layouts: Vec<Language>,
+    /// Keys that switch to the next layout.
+    switch_keys: Vec<String>,
+    /// LED that is on when the second layout is active (the `grp_led` XKB option).
+    switch_led: Option<String>,
+    /// Prior probability (from 0 to 1) of the language of the active layout.
+    prior_weight: f64,
+    /// Keys emulated to switch the layout when retyping a word, `switch-keys` by default.
+    emulated_switch_keys: Vec<String>,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            layouts: vec![],
+            switch_keys: vec![],
+            switch_led: None,
+            prior_weight: 0.7,
+            emulated_switch_keys: vec![],
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PasteConfig {
+    method: PasteMethod,
+    /// Delay after each typed key in milliseconds.
+    key_delay_ms: u64,
+}
+
+impl Default for PasteConfig {
+    fn default() -> Self {
+        Self {
+            method: PasteMethod::Clipboard,
+            key_delay_ms: 5,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct KeyboardConfig {
+    /// Maximum time between the steps of key sequences in milliseconds.
+    sequence_timeout_ms: Option<u64>,
+}
+
+/// Keyboards to listen to, e.g. to skip macro pads and security keys.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct DevicesConfig {
+    /// Only the matching devices are used if any rule is set.
+    include: Vec<DeviceRuleConfig>,
+    exclude: Vec<DeviceRuleConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceRuleConfig {
+    /// Regular expression of the device name.
+    name: Option<String>,
+    /// Vendor id like `0x1050`.
+    vendor: Option<u16>,
+    /// Product id like `0x0407`.
+    product: Option<u16>,
+    /// Device node or `/dev/input/by-id` link.
+    path: Option<PathBuf>,
+}
+
+impl DeviceRuleConfig {
+    fn rule(&self) -> Result<DeviceRule, CliCommandError> {
+        let name = self
+            .name
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|err| CliCommandError::SetupDevices(err.to_string()))?;
+        Ok(DeviceRule {
+            name,
+            vendor: self.vendor,
+            product: self.product,
+            path: self.path.clone(),
+        })
+    }
+}
+
+/// Line and column (starting from 1) of the byte offset in the text.
+fn position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+/// Toml error with its position in the text.
+fn toml_error(text: &str, err: &toml::de::Error) -> String {
+    let message = err.message().trim_end();
+    match err.span() {
+        Some(span) => {
+            let (line, column) = position(text, span.start);
+            format!("line {line}, column {column}: {message}")
+        }
+        None => message.to_string(),
+    }
+}
+
+/// Configuration of the old format: the bindings and the startup commands in one list.
+#[derive(Deserialize)]
+struct LegacyConfig {
+    commands: Vec<LegacyCommand>,
+    #[serde(flatten)]
+    rest: toml::Table,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct LegacyCommand {
+    handler: LegacyCommandHandler,
+    /// Program of a startup command or action of a binding.
     name: String,
+    /// Arguments of a startup command or keys of a binding.
     #[serde(default)]
     args: Vec<String>,
+    keys: Option<String>,
+    #[serde(default)]
+    trigger: TriggerConfig,
+    trigger_ms: Option<u64>,
+    #[serde(default)]
+    grab: bool,
+    #[serde(flatten)]
+    options: toml::Table,
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
+#[derive(Deserialize)]
 #[serde(rename_all = "kebab-case")]
-pub enum CliCommandHandler {
+enum LegacyCommandHandler {
     BashStarter,
     ActionListener,
 }
+
+/// Delay of the old format before each startup command.
+const LEGACY_STARTUP_DELAY_MS: u64 = 300;
+
+#[derive(Serialize)]
+struct MigratedConfig {
+    version: i64,
+    binding: Vec<BindingConfig>,
+    startup: Vec<StartupConfig>,
+    #[serde(flatten)]
+    rest: toml::Table,
+}
+
+/// Configuration of the old format converted to the current one, the other sections are kept.
+fn migrate_config(data: &str) -> Result<String, String> {
+    let legacy: LegacyConfig = toml::from_str(data).map_err(|err| toml_error(data, &err))?;
+    let mut binding = vec![];
+    let mut startup = vec![];
+    for command in legacy.commands {
+        match command.handler {
+            LegacyCommandHandler::BashStarter => startup.push(StartupConfig {
+                command: [vec![command.name], command.args].concat(),
+                cwd: None,
+                env: BTreeMap::new(),
+                name: None,
+                after: vec![],
+                delay_ms: LEGACY_STARTUP_DELAY_MS,
+                ready_delay_ms: 0,
+                restart: RestartPolicy::Never,
+                restart_delay_ms: None,
+                max_restart_delay_ms: None,
+                log: None,
+            }),
+            LegacyCommandHandler::ActionListener => {
+                let keys = match command.keys {
+                    Some(_) if !command.args.is_empty() => {
+                        return Err(format!(
+                            "both keys and args are specified for {}",
+                            command.name
+                        ))
+                    }
+                    Some(keys) => keys,
+                    None => command.args.join("+"),
+                };
+                binding.push(BindingConfig {
+                    keys,
+                    action: command.name,
+                    trigger: command.trigger,
+                    trigger_ms: command.trigger_ms,
+                    grab: command.grab,
+                    options: command.options,
+                });
+            }
+        }
+    }
+
+    let config = MigratedConfig {
+        version: CONFIG_VERSION,
+        binding,
+        startup,
+        rest: legacy.rest,
+    };
+    toml::to_string_pretty(&config).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::builtin_actions;
+
+    const LEGACY_CONFIG: &str = r#"
+[[commands]]
+handler = "bash-starter"
+name = "dhe_gui"
+args = ["--hidden"]
+
+[[commands]]
+handler = "action-listener"
+name = "translate-to-paste"
+args = ["LCtrl", "T"]
+target-language = "ru"
+
+[[commands]]
+handler = "action-listener"
+name = "fix-layout"
+keys = "LCtrl+Space, F"
+trigger = "double-tap"
+
+[paste]
+method = "typing"
+"#;
+
+    #[test]
+    fn migrate_legacy_config() {
+        let migrated = migrate_config(LEGACY_CONFIG).unwrap();
+        let config = CliCommandsConfig::from_toml(&migrated).unwrap();
+
+        assert!(config.source.is_some());
+        assert_eq!(PasteMethod::Typing, config.paste.method);
+        let bindings: Vec<_> = config.binding.iter().map(Spanned::get_ref).collect();
+        assert_eq!(2, bindings.len());
+        assert_eq!("LCtrl+T", bindings[0].keys);
+        assert_eq!(
+            Some(&toml::Value::from("ru")),
+            bindings[0].options.get("target-language")
+        );
+        assert_eq!(TriggerConfig::DoubleTap, bindings[1].trigger);
+        let startup = &config.startup_commands().unwrap()[0];
+        assert_eq!("dhe_gui", startup.program);
+        assert_eq!(vec!["--hidden"], startup.args);
+        assert_eq!(
+            Duration::from_millis(LEGACY_STARTUP_DELAY_MS),
+            startup.delay
+        );
+        assert!(config.action_listener_params(&builtin_actions()).is_ok());
+
+        // The old format is still read.
+        let config = CliCommandsConfig::from_toml(LEGACY_CONFIG).unwrap();
+        assert!(config.source.is_none());
+        assert_eq!(2, config.binding.len());
+    }
+
+    #[test]
+    fn error_positions() {
+        let err = CliCommandsConfig::from_toml("version = 2\n\n[[binding]]\nkeys = 5\n")
+            .err()
+            .unwrap();
+        assert!(err.starts_with("line 4, column 8:"), "{err}");
+
+        let err = CliCommandsConfig::from_toml("version = 3\n").err().unwrap();
+        assert!(err.contains("unsupported configuration version 3"), "{err}");
+
+        let config = CliCommandsConfig::from_toml(
+            r#"
+version = 2
+
+[[binding]]
+keys = "LCtrl+T"
+action = "translate-to-paste"
+
+[[binding]]
+keys = "LCtrl+F"
+action = "fix-layout"
+target-language = "ru"
+"#,
+        )
+        .unwrap();
+        let err = config
+            .action_listener_params(&builtin_actions())
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string()
+                .starts_with("wrong binding \"fix-layout\" at line 8:"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn startup_dependencies() {
+        let config = CliCommandsConfig::from_toml(
+            r#"
+version = 2
+
+[[startup]]
+command = ["/usr/bin/server", "--port", "8080"]
+ready-delay-ms = 500
+restart = "on-failure"
+restart-delay-ms = 100
+
+[[startup]]
+name = "client"
+command = ["server"]
+after = ["server"]
+"#,
+        )
+        .unwrap();
+        let commands = config.startup_commands().unwrap();
+        assert_eq!("server", commands[0].name);
+        assert_eq!(Duration::from_millis(100), commands[0].backoff.delay);
+        assert_eq!(vec!["server"], commands[1].after);
+
+        let wrong = |startup: &str| {
+            CliCommandsConfig::from_toml(&format!("version = 2\n{startup}"))
+                .unwrap()
+                .startup_commands()
+                .err()
+                .unwrap()
+                .to_string()
+        };
+        let err = wrong(
+            r#"
+[[startup]]
+command = ["client"]
+after = ["server"]
+
+[[startup]]
+command = ["server"]
+"#,
+        );
+        assert!(
+            err.contains("server is not a startup command listed before"),
+            "{err}"
+        );
+        let err = wrong(
+            r#"
+[[startup]]
+command = ["server"]
+
+[[startup]]
+command = ["/opt/server"]
+"#,
+        );
+        assert!(err.contains("name server is already used"), "{err}");
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use inotify::{Inotify, WatchMask};
+use tokio::{
+    io::unix::AsyncFd,
+    signal::unix::{signal, Signal, SignalKind},
+    time::{sleep_until, Instant},
+};
+
+/// Time to wait for the other writes of an editor saving the file.
+const SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+/// Requests to reload the configuration: changes of the configuration file and SIGHUP.
+pub struct ConfigWatcher {
+    watcher: AsyncFd<Inotify>,
+    file_name: OsString,
+    hangup: Signal,
+    /// When the requested reload happens, kept here so that a dropped wait doesn't lose it.
+    reload_at: Option<Instant>,
+}
+
+impl ConfigWatcher {
+    /// The directory of the file is watched, since editors often replace the file with a new one.
+    pub fn new(path: &Path) -> io::Result<Self> {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?
+            .to_os_string();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let watcher = Inotify::init()?;
+        watcher.watches().add(
+            dir,
+            WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE,
+        )?;
+        Ok(Self {
+            watcher: AsyncFd::new(watcher)?,
+            file_name,
+            hangup: signal(SignalKind::hangup())?,
+            reload_at: None,
+        })
+    }
+
+    /// Wait until the configuration should be reloaded.
+    /// The wait is cancel safe, a change seen before the future is dropped is reported by the next one.
+    pub async fn changed(&mut self) -> io::Result<()> {
+        while self.reload_at.is_none() {
+            tokio::select! {
+                _ = self.hangup.recv() => self.reload_at = Some(Instant::now()),
+                changed = file_changed(&mut self.watcher, &self.file_name) => {
+                    if changed? {
+                        self.reload_at = Some(Instant::now() + SETTLE_DELAY);
+                    }
+                }
+            }
+        }
+        if let Some(reload_at) = self.reload_at {
+            sleep_until(reload_at).await;
+        }
+        self.reload_at = None;
+
+        // The changes made while waiting are covered by this reload.
+        let mut buffer = [0; 1024];
+        loop {
+            match self.watcher.get_mut().read_events(&mut buffer) {
+                Ok(_) => continue,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// Whether the next read events of the directory include the file.
+async fn file_changed(watcher: &mut AsyncFd<Inotify>, file_name: &OsString) -> io::Result<bool> {
+    let mut buffer = [0; 1024];
+    loop {
+        let mut guard = watcher.readable_mut().await?;
+        let read = guard.try_io(|watcher| {
+            let mut events = watcher.get_mut().read_events(&mut buffer)?;
+            Ok(events.any(|event| event.name == Some(file_name.as_os_str())))
+        });
+        if let Ok(changed) = read {
+            return changed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tokio::time::{sleep, timeout};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn file_changes() {
+        let dir = std::env::temp_dir().join(format!("dhe_config_watcher_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("dhe_commands.toml");
+        fs::write(&path, "version = 2\n").unwrap();
+        let mut watcher = ConfigWatcher::new(&path).unwrap();
+
+        fs::write(dir.join("other.toml"), "").unwrap();
+        assert!(timeout(SETTLE_DELAY * 2, watcher.changed()).await.is_err());
+
+        // Saved as a new file renamed over the old one.
+        fs::write(dir.join("dhe_commands.toml.tmp"), "version = 2\n").unwrap();
+        fs::rename(dir.join("dhe_commands.toml.tmp"), &path).unwrap();
+        assert!(timeout(SETTLE_DELAY * 5, watcher.changed()).await.is_ok());
+
+        // E.g. the key releases after Ctrl+S are read while the change settles.
+        fs::write(&path, "version = 2\n").unwrap();
+        tokio::select! {
+            _ = watcher.changed() => panic!("the change is reported before it settles"),
+            _ = sleep(SETTLE_DELAY / 2) => {}
+        }
+        assert!(timeout(SETTLE_DELAY, watcher.changed()).await.is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
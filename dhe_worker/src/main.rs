@@ -1,11 +1,23 @@
 mod action_listener;
+mod actions;
 mod cli_command;
+mod config_watcher;
+mod shell_command;
+mod startup;
 
-use clap::Parser;
-use dhe_sdk::setup_logs;
-use tracing::Level;
+use std::{fs, process};
 
-use crate::{action_listener::start_action_listener_loop, cli_command::CliCommandsConfig};
+use clap::{Parser, Subcommand};
+use dhe_sdk::{keyboard::input_devices, setup_logs};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{info, Level};
+
+use crate::{
+    action_listener::{start_action_listener_loop, ConfigReload},
+    actions::builtin_actions,
+    cli_command::CliCommandsConfig,
+    startup::{Supervisor, SupervisorStatus},
+};
 
 #[derive(Parser)]
 #[clap(version, about, long_about)]
@@ -14,6 +26,22 @@ struct Cli {
     commands_file: Option<String>,
     #[arg(short, long, default_value_t = false)]
     init: bool,
+    #[command(subcommand)]
+    command: Option<CliSubcommand>,
+}
+
+#[derive(Subcommand)]
+enum CliSubcommand {
+    /// List keyboards and pointers and whether the device filter of the config matches them
+    Devices,
+    /// Convert the config of the old format with a `commands` list to the current one
+    MigrateConfig {
+        /// Replace the config file, the old one is kept with the `.bak` extension
+        #[arg(long, default_value_t = false)]
+        in_place: bool,
+    },
+    /// Show the startup commands of the running worker
+    Status,
 }
 
 #[tokio::main]
@@ -22,14 +50,130 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let Cli {
         commands_file,
         init,
+        command,
     } = Cli::parse();
 
-    let commands_config = CliCommandsConfig::parse(commands_file).unwrap();
-    if init {
-        commands_config.execute_bash_starter_commands().unwrap();
+    if let Some(CliSubcommand::Status) = command {
+        print_status()?;
+        return Ok(());
+    }
+
+    let path = CliCommandsConfig::path(commands_file)?;
+    if let Some(CliSubcommand::MigrateConfig { in_place }) = command {
+        let migrated = CliCommandsConfig::migrate_file(&path)?;
+        if in_place {
+            fs::copy(&path, path.with_extension("toml.bak"))?;
+            fs::write(&path, migrated)?;
+            println!("migrated {}", path.display());
+        } else {
+            print!("{migrated}");
+        }
+        return Ok(());
     }
 
-    start_action_listener_loop(commands_config.action_listener_params()?.into_iter()).await?;
+    let commands_config = match CliCommandsConfig::parse(Some(&path)) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+    };
+    if let Some(CliSubcommand::Devices) = command {
+        let device_filter = commands_config.device_filter()?;
+        for device in input_devices() {
+            let mark = if device_filter.matches(&device) {
+                "+"
+            } else {
+                "-"
+            };
+            println!(
+                "{mark} {} {:?} {:04x}:{:04x} {}",
+                device.kind,
+                device.name,
+                device.vendor,
+                device.product,
+                device.path.display()
+            );
+            for link in &device.links {
+                println!("      {}", link.display());
+            }
+        }
+        return Ok(());
+    }
+
+    let translator = commands_config.translator()?;
+    let detector = commands_config.source_language_detector()?;
+    let registry = builtin_actions();
+    let params = commands_config.action_listener_params(&registry)?;
+    let listener_settings = commands_config.listener_settings()?;
+    let startup_commands = if init {
+        commands_config.startup_commands()?
+    } else {
+        vec![]
+    };
+
+    // Started once the configuration is checked, the errors below go through the shutdown.
+    let supervisor =
+        init.then(|| Supervisor::start(startup_commands, SupervisorStatus::default_path()));
+    let listener = start_action_listener_loop(
+        params,
+        translator,
+        detector,
+        listener_settings,
+        commands_config.paste_settings(),
+        ConfigReload { path, registry },
+    );
+    let result = tokio::select! {
+        result = listener => result,
+        _ = shutdown_signal() => {
+            info!("shutting down");
+            Ok(())
+        }
+    };
+    // The startup commands are stopped even if the listener failed.
+    if let Some(supervisor) = supervisor {
+        supervisor.shutdown().await;
+    }
+    result?;
 
     Ok(())
 }
+
+/// SIGTERM or SIGINT.
+async fn shutdown_signal() {
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = terminate.recv() => {}
+                _ = tokio::signal::ctrl_c() => {}
+            }
+        }
+        Err(_) => {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+fn print_status() -> Result<(), Box<dyn std::error::Error>> {
+    let Some(status) = SupervisorStatus::read(&SupervisorStatus::default_path())? else {
+        println!("no startup commands are supervised, start the worker with --init");
+        return Ok(());
+    };
+    if !status.is_worker_alive() {
+        println!(
+            "worker {} is not running, the status may be outdated",
+            status.worker_pid
+        );
+    }
+    for process in status.process {
+        println!("{} {}", process.name, process.state);
+        println!(
+            "      since {}, restarts: {}",
+            process.since, process.restarts
+        );
+        if let Some(log) = process.log {
+            println!("      log {}", log.display());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use std::{process::Stdio, time::Duration};
+
+use anyhow::{anyhow, bail};
+use serde::Deserialize;
+use tokio::{io::AsyncWriteExt, process::Command, time::timeout};
+
+/// Environment variable with the input text when it is passed through the environment.
+pub const TEXT_ENV: &str = "DHE_TEXT";
+/// Time the command is given to finish by default.
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Text given to the command.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShellInput {
+    /// The selected text.
+    #[default]
+    Primary,
+    Clipboard,
+    None,
+}
+
+/// The way the input text is passed to the command.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShellInputPassing {
+    #[default]
+    Stdin,
+    /// The `DHE_TEXT` environment variable.
+    Env,
+}
+
+/// What is done with the output of the command.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShellOutput {
+    /// Paste in place of the selection.
+    #[default]
+    Paste,
+    Notify,
+    Clipboard,
+    None,
+}
+
+/// User command run by the `run-command` action.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ShellCommand {
+    /// Program and its arguments.
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub input: ShellInput,
+    /// Whether the text goes to stdin of the command or to the `DHE_TEXT` variable.
+    #[serde(default)]
+    pub input_via: ShellInputPassing,
+    /// What is done with stdout of the command.
+    #[serde(default)]
+    pub output: ShellOutput,
+    /// Time the command is given to finish in milliseconds, it is killed after it.
+    pub timeout_ms: Option<u64>,
+}
+
+impl ShellCommand {
+    /// Run the command with the input text, returns its output without the trailing newline.
+    /// The input is written while the output is read, so a filter doesn't block on a full pipe.
+    pub async fn execute(&self, text: Option<String>) -> anyhow::Result<String> {
+        let Some((program, args)) = self.command.split_first() else {
+            bail!("command is not specified")
+        };
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let stdin_text = match (self.input_via, text) {
+            (ShellInputPassing::Stdin, Some(text)) => {
+                command.stdin(Stdio::piped());
+                Some(text)
+            }
+            (ShellInputPassing::Env, Some(text)) => {
+                command.env(TEXT_ENV, text).stdin(Stdio::null());
+                None
+            }
+            (_, None) => {
+                command.stdin(Stdio::null());
+                None
+            }
+        };
+
+        let mut child = command.spawn()?;
+        let stdin = child.stdin.take();
+        let write = async move {
+            if let (Some(text), Some(mut stdin)) = (stdin_text, stdin) {
+                // The command may exit without reading its input.
+                if let Err(err) = stdin.write_all(text.as_bytes()).await {
+                    if err.kind() != std::io::ErrorKind::BrokenPipe {
+                        return Err(err);
+                    }
+                }
+            }
+            // Stdin is closed here, so the command sees the end of the input.
+            Ok(())
+        };
+        let limit = self
+            .timeout_ms
+            .map_or(DEFAULT_COMMAND_TIMEOUT, Duration::from_millis);
+        // The child is killed when it is dropped on the timeout.
+        let (written, output) = timeout(limit, async {
+            tokio::join!(write, child.wait_with_output())
+        })
+        .await
+        .map_err(|_| anyhow!("command {program} didn't finish in {limit:?}"))?;
+        written?;
+        let output = output?;
+        if !output.status.success() {
+            bail!(
+                "command {} failed with {}: {}",
+                program,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        }
+
+        let mut stdout = String::from_utf8(output.stdout)?;
+        if stdout.ends_with('\n') {
+            stdout.pop();
+        }
+        Ok(stdout)
+    }
+}
@@ -0,0 +1,572 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    fs::OpenOptions,
+    future::Future,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tokio::{
+    process::{Child, Command},
+    sync::watch,
+    task::JoinSet,
+    time::{sleep, timeout, Instant},
+};
+use tracing::{error, info, warn};
+
+/// Time given to a command to exit after SIGTERM before it is killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// When a startup command is started again after it exits.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    #[default]
+    Never,
+    /// Restart when the command exits with an error or is killed by a signal.
+    OnFailure,
+    Always,
+}
+
+impl RestartPolicy {
+    fn restarts(&self, status: ExitStatus) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => !status.success(),
+            RestartPolicy::Always => true,
+        }
+    }
+}
+
+/// Delay before a restart, doubled after each restart up to the maximum one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RestartBackoff {
+    pub delay: Duration,
+    /// The delay is reset when the command runs longer than the maximum delay.
+    pub max_delay: Duration,
+}
+
+impl Default for RestartBackoff {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Command started with the worker.
+#[derive(Debug, Clone)]
+pub struct StartupCommand {
+    /// Name used by the dependencies and the status.
+    pub name: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub env: BTreeMap<String, String>,
+    /// Delay before the command is started after the previous one.
+    pub delay: Duration,
+    /// Commands that have to be ready before this one is started.
+    pub after: Vec<String>,
+    /// Time after the start when the running command is considered ready.
+    pub ready_delay: Duration,
+    pub restart: RestartPolicy,
+    pub backoff: RestartBackoff,
+    /// File stdout and stderr of the command are appended to, the ones of the worker otherwise.
+    pub log: Option<PathBuf>,
+}
+
+/// State of a startup command.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "state", rename_all = "kebab-case")]
+pub enum ProcessState {
+    /// Waiting for the previous command, the dependencies or the delay.
+    Waiting,
+    /// Started, but not ready yet.
+    Starting {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pid: Option<u32>,
+    },
+    Running {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pid: Option<u32>,
+    },
+    /// Exited and waiting for the restart.
+    Restarting {
+        status: String,
+    },
+    Exited {
+        status: String,
+    },
+    /// Couldn't be started.
+    Failed {
+        error: String,
+    },
+    /// Not started since the dependency exited without getting ready.
+    Skipped {
+        dependency: String,
+    },
+    /// Stopped on the worker shutdown.
+    Stopped,
+}
+
+impl ProcessState {
+    fn is_ready(&self) -> bool {
+        matches!(self, ProcessState::Running { .. })
+    }
+
+    /// Whether the command won't be started again.
+    fn is_done(&self) -> bool {
+        matches!(
+            self,
+            ProcessState::Exited { .. }
+                | ProcessState::Failed { .. }
+                | ProcessState::Skipped { .. }
+                | ProcessState::Stopped
+        )
+    }
+}
+
+impl fmt::Display for ProcessState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessState::Waiting => write!(f, "waiting"),
+            ProcessState::Starting { pid: Some(pid) } => write!(f, "starting (pid {pid})"),
+            ProcessState::Starting { pid: None } => write!(f, "starting"),
+            ProcessState::Running { pid: Some(pid) } => write!(f, "running (pid {pid})"),
+            ProcessState::Running { pid: None } => write!(f, "running"),
+            ProcessState::Restarting { status } => write!(f, "restarting after {status}"),
+            ProcessState::Exited { status } => write!(f, "exited with {status}"),
+            ProcessState::Failed { error } => write!(f, "failed: {error}"),
+            ProcessState::Skipped { dependency } => {
+                write!(f, "skipped, {dependency} didn't get ready")
+            }
+            ProcessState::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+/// Startup command in the status file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProcessStatus {
+    pub name: String,
+    pub state: ProcessState,
+    /// Time of the last state change in RFC 3339.
+    pub since: String,
+    pub restarts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log: Option<PathBuf>,
+}
+
+/// Status of the startup commands written by the running worker.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SupervisorStatus {
+    pub worker_pid: u32,
+    #[serde(default)]
+    pub process: Vec<ProcessStatus>,
+}
+
+impl SupervisorStatus {
+    /// Default path to the status file in the XDG runtime directory.
+    pub fn default_path() -> PathBuf {
+        dirs::runtime_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("dhe")
+            .join("startup_status.toml")
+    }
+
+    /// Status written by the worker, `None` if no worker supervises the commands.
+    pub fn read(path: &Path) -> io::Result<Option<Self>> {
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        toml::from_str(&data)
+            .map(Some)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Whether the worker that wrote the status is still running.
+    pub fn is_worker_alive(&self) -> bool {
+        Path::new("/proc")
+            .join(self.worker_pid.to_string())
+            .exists()
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let data = toml::to_string(self).map_err(io::Error::other)?;
+        // Replaced at once, so a reader never sees a partly written file.
+        let tmp = path.with_extension("toml.tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(tmp, path)
+    }
+}
+
+fn now() -> String {
+    OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .unwrap_or_default()
+}
+
+/// State of one command, seen by the commands that depend on it and saved to the status file.
+struct Process {
+    index: usize,
+    state: watch::Sender<ProcessState>,
+    status: Arc<Mutex<SupervisorStatus>>,
+    status_path: PathBuf,
+}
+
+impl Process {
+    fn set(&self, state: ProcessState) {
+        let mut status = self.status.lock().unwrap();
+        let process = &mut status.process[self.index];
+        if matches!(state, ProcessState::Restarting { .. }) {
+            process.restarts += 1;
+        }
+        process.state = state.clone();
+        process.since = now();
+        if let Err(err) = status.write(&self.status_path) {
+            warn!("failed to write startup status: {err}");
+        }
+        drop(status);
+        self.state.send_replace(state);
+    }
+}
+
+/// Output of the future, `None` if the supervisor is stopped first.
+async fn until_stopped<F: Future>(
+    stop: &mut watch::Receiver<bool>,
+    future: F,
+) -> Option<F::Output> {
+    tokio::select! {
+        output = future => Some(output),
+        _ = stopped(stop) => None,
+    }
+}
+
+async fn stopped(stop: &mut watch::Receiver<bool>) {
+    // An error means the supervisor is dropped, the commands are stopped too.
+    let _ = stop.wait_for(|stop| *stop).await;
+}
+
+/// The command is ready or won't be started again.
+async fn settled(mut state: watch::Receiver<ProcessState>) -> bool {
+    match state
+        .wait_for(|state| state.is_ready() || state.is_done())
+        .await
+    {
+        Ok(state) => state.is_ready(),
+        // The task of the command is finished.
+        Err(_) => false,
+    }
+}
+
+impl StartupCommand {
+    fn command(&self) -> io::Result<Command> {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .envs(&self.env)
+            .stdin(Stdio::null());
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+        if let Some(log) = &self.log {
+            if let Some(dir) = log.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut file = OpenOptions::new().create(true).append(true).open(log)?;
+            writeln!(file, "--- {} {} {:?}", now(), self.program, self.args)?;
+            command.stdout(file.try_clone()?).stderr(file);
+        }
+        Ok(command)
+    }
+
+    /// Wait for the previous command and the dependencies, then run the command
+    /// until it exits without a restart by the policy or the supervisor is stopped.
+    async fn supervise(
+        self,
+        process: Process,
+        previous: Option<watch::Receiver<ProcessState>>,
+        dependencies: Vec<(String, watch::Receiver<ProcessState>)>,
+        mut stop: watch::Receiver<bool>,
+    ) {
+        if let Some(mut previous) = previous {
+            let started = async {
+                let _ = previous
+                    .wait_for(|state| *state != ProcessState::Waiting)
+                    .await;
+            };
+            if until_stopped(&mut stop, started).await.is_none() {
+                return process.set(ProcessState::Stopped);
+            }
+        }
+        for (dependency, state) in dependencies {
+            match until_stopped(&mut stop, settled(state)).await {
+                Some(true) => {}
+                Some(false) => {
+                    warn!(
+                        "startup command {} is skipped, {dependency} didn't get ready",
+                        self.name
+                    );
+                    return process.set(ProcessState::Skipped { dependency });
+                }
+                None => return process.set(ProcessState::Stopped),
+            }
+        }
+        if until_stopped(&mut stop, sleep(self.delay)).await.is_none() {
+            return process.set(ProcessState::Stopped);
+        }
+
+        let mut restart_delay = self.backoff.delay;
+        loop {
+            let mut child = match self.command().and_then(|mut command| command.spawn()) {
+                Ok(child) => child,
+                Err(err) => {
+                    // A command that can't be started is not retried.
+                    error!(
+                        "startup command {} {:?} error: {err}",
+                        self.program, self.args
+                    );
+                    return process.set(ProcessState::Failed {
+                        error: err.to_string(),
+                    });
+                }
+            };
+            let pid = child.id();
+            process.set(ProcessState::Starting { pid });
+
+            let started = Instant::now();
+            let mut ready = false;
+            let status = loop {
+                tokio::select! {
+                    status = child.wait() => break status,
+                    _ = sleep(self.ready_delay), if !ready => {
+                        ready = true;
+                        process.set(ProcessState::Running { pid });
+                    }
+                    _ = stopped(&mut stop) => {
+                        stop_child(&mut child, pid, &self.name).await;
+                        return process.set(ProcessState::Stopped);
+                    }
+                }
+            };
+
+            let status = match status {
+                Ok(status) if self.restart.restarts(status) => status,
+                Ok(status) => {
+                    info!("startup command {} exited with {status}", self.name);
+                    return process.set(ProcessState::Exited {
+                        status: status.to_string(),
+                    });
+                }
+                Err(err) => {
+                    error!("startup command {} error: {err}", self.name);
+                    return process.set(ProcessState::Failed {
+                        error: err.to_string(),
+                    });
+                }
+            };
+            if started.elapsed() > self.backoff.max_delay {
+                restart_delay = self.backoff.delay;
+            }
+            warn!(
+                "startup command {} exited with {status}, restarting in {restart_delay:?}",
+                self.name
+            );
+            process.set(ProcessState::Restarting {
+                status: status.to_string(),
+            });
+            if until_stopped(&mut stop, sleep(restart_delay))
+                .await
+                .is_none()
+            {
+                return process.set(ProcessState::Stopped);
+            }
+            restart_delay = (restart_delay * 2).min(self.backoff.max_delay);
+        }
+    }
+}
+
+/// Ask the child to exit with SIGTERM and kill it if it doesn't in time.
+/// Without the pid the child is killed at once.
+async fn stop_child(child: &mut Child, pid: Option<u32>, name: &str) {
+    // kill(0) would signal the whole process group of the worker.
+    match pid.and_then(|pid| libc::pid_t::try_from(pid).ok().filter(|&pid| pid > 0)) {
+        // SAFETY: kill doesn't touch memory. The pid is positive, so only one process
+        // is signaled, and it is still the child since the child isn't reaped before it exits.
+        Some(pid) => unsafe {
+            libc::kill(pid, libc::SIGTERM);
+        },
+        None => {
+            if let Err(err) = child.start_kill() {
+                error!("failed to kill startup command {name}: {err}");
+            }
+        }
+    }
+    if timeout(STOP_TIMEOUT, child.wait()).await.is_err() {
+        warn!("startup command {name} didn't stop in {STOP_TIMEOUT:?}, killing it");
+        if let Err(err) = child.kill().await {
+            error!("failed to kill startup command {name}: {err}");
+        }
+    }
+}
+
+/// Runs the startup commands in the background and keeps the status file up to date.
+pub struct Supervisor {
+    stop: watch::Sender<bool>,
+    tasks: JoinSet<()>,
+    status_path: PathBuf,
+}
+
+impl Supervisor {
+    /// Start the commands one after another, each after its delay and once its dependencies
+    /// are ready. The dependencies have to be listed before the command.
+    pub fn start(commands: Vec<StartupCommand>, status_path: PathBuf) -> Self {
+        let status = Arc::new(Mutex::new(SupervisorStatus {
+            worker_pid: std::process::id(),
+            process: commands
+                .iter()
+                .map(|command| ProcessStatus {
+                    name: command.name.clone(),
+                    state: ProcessState::Waiting,
+                    since: now(),
+                    restarts: 0,
+                    log: command.log.clone(),
+                })
+                .collect(),
+        }));
+        if let Err(err) = status.lock().unwrap().write(&status_path) {
+            warn!("failed to write startup status: {err}");
+        }
+
+        let (stop, _) = watch::channel(false);
+        let mut tasks = JoinSet::new();
+        let mut states: HashMap<String, watch::Receiver<ProcessState>> = HashMap::new();
+        let mut previous = None;
+        for (index, command) in commands.into_iter().enumerate() {
+            let (state, receiver) = watch::channel(ProcessState::Waiting);
+            let dependencies = command
+                .after
+                .iter()
+                .filter_map(|name| Some((name.clone(), states.get(name)?.clone())))
+                .collect();
+            let process = Process {
+                index,
+                state,
+                status: status.clone(),
+                status_path: status_path.clone(),
+            };
+            states.insert(command.name.clone(), receiver.clone());
+            tasks.spawn(command.supervise(
+                process,
+                previous.replace(receiver),
+                dependencies,
+                stop.subscribe(),
+            ));
+        }
+
+        Self {
+            stop,
+            tasks,
+            status_path,
+        }
+    }
+
+    /// Stop the running commands and wait until they exit.
+    pub async fn shutdown(mut self) {
+        self.stop.send_replace(true);
+        while self.tasks.join_next().await.is_some() {}
+        if let Err(err) = fs::remove_file(&self.status_path) {
+            warn!("failed to remove startup status: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(name: &str, program: &str, args: &[&str]) -> StartupCommand {
+        StartupCommand {
+            name: name.to_string(),
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            cwd: None,
+            env: BTreeMap::new(),
+            delay: Duration::ZERO,
+            after: vec![],
+            ready_delay: Duration::ZERO,
+            restart: RestartPolicy::Never,
+            backoff: RestartBackoff::default(),
+            log: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn supervise_commands() {
+        let dir = std::env::temp_dir().join(format!("dhe_supervisor_{}", std::process::id()));
+        let status_path = dir.join("status.toml");
+        let log = dir.join("client.log");
+
+        let server = StartupCommand {
+            ready_delay: Duration::from_millis(100),
+            ..command("server", "sleep", &["30"])
+        };
+        let client = StartupCommand {
+            after: vec!["server".to_string()],
+            log: Some(log.clone()),
+            ..command("client", "sh", &["-c", "echo started"])
+        };
+        let flaky = StartupCommand {
+            restart: RestartPolicy::OnFailure,
+            backoff: RestartBackoff {
+                delay: Duration::from_millis(10),
+                max_delay: Duration::from_millis(40),
+            },
+            ..command("flaky", "sh", &["-c", "exit 1"])
+        };
+        let broken = command("broken", "/nonexistent/dhe_command", &[]);
+        let dependent = StartupCommand {
+            after: vec!["broken".to_string()],
+            ..command("dependent", "true", &[])
+        };
+        let supervisor = Supervisor::start(
+            vec![server, client, flaky, broken, dependent],
+            status_path.clone(),
+        );
+        sleep(Duration::from_millis(500)).await;
+
+        let status = SupervisorStatus::read(&status_path).unwrap().unwrap();
+        assert!(status.is_worker_alive());
+        let states: Vec<_> = status.process.iter().map(|p| &p.state).collect();
+        assert!(matches!(states[0], ProcessState::Running { .. }));
+        assert!(matches!(states[1], ProcessState::Exited { .. }));
+        assert!(status.process[2].restarts >= 3, "{:?}", status.process[2]);
+        assert!(matches!(states[3], ProcessState::Failed { .. }));
+        assert_eq!(
+            &ProcessState::Skipped {
+                dependency: "broken".to_string()
+            },
+            states[4]
+        );
+        assert!(fs::read_to_string(&log).unwrap().ends_with("started\n"));
+
+        let started = Instant::now();
+        supervisor.shutdown().await;
+        assert!(started.elapsed() < STOP_TIMEOUT);
+        assert!(SupervisorStatus::read(&status_path).unwrap().is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}